@@ -3,10 +3,10 @@ const fs = require("fs").promises;
 const axios = require("axios");
 const eachDayOfInterval = require("date-fns/eachDayOfInterval");
 const addDays = require("date-fns/addDays");
+const format = require("date-fns/format");
 const _ = require("lodash");
 
 const DAYS_OF_DATA = 7;
-const CURVE_DEGREE = 3;
 
 const ACCESS_TOKEN = process.env.ACCESS_TOKEN;
 
@@ -22,15 +22,6 @@ const getActivitesUrl = date =>
         .toISOString()
         .slice(0, 10)}`;
 
-const DIVIDER = 50;
-
-const scaleFunc = maxValue => v => {
-    let f = 1 - v / maxValue;
-    let curved = f ** CURVE_DEGREE;
-    let inv = 1 - curved;
-    return Math.round((inv * maxValue) / DIVIDER);
-};
-
 async function main() {
     const [dateArg] = process.argv.slice(2);
 
@@ -58,31 +49,23 @@ async function main() {
     const dataByDay = await Promise.all(promises);
 
     const allKeys = _.uniq(_.flatten(dataByDay.map(d => d.map(i => i.key))));
-    let maxValue = 0;
+    // Raw counts, the simulator turns them into nodes
     const groups = allKeys.map((key, index) => {
-        const day_values = _.range(0, DAYS_OF_DATA + 1).map(i => {
+        const counts = _.range(0, DAYS_OF_DATA + 1).map(i => {
             const dayValue = dataByDay[i].find(d => d.key === key);
             return dayValue ? Math.floor(dayValue.count) : 0;
         });
-        maxValue = Math.max(maxValue, ...day_values);
         return {
             key,
             index,
-            day_values,
+            counts,
         };
     });
-    const scale = scaleFunc(maxValue);
-
-    const scaledGroups = groups.map(g => ({
-        ...g,
-        display_values: g.day_values,
-        day_values: g.day_values.map(scale),
-    }));
 
     const output = {
-        start_date: startDate.toLocaleDateString().replace(/\//g, "-"),
-        end_date: endDate.toLocaleDateString().replace(/\//g, "-"),
-        groups: scaledGroups,
+        start_date: format(startDate, "yyyy-MM-dd"),
+        end_date: format(endDate, "yyyy-MM-dd"),
+        groups,
     };
 
     await fs.writeFile("../data.json", JSON.stringify(output), { encoding: "utf-8" });
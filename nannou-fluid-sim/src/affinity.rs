@@ -0,0 +1,103 @@
+// How strongly each pair of topics attracts or repels the other.
+use crate::hierarchy::related;
+use crate::{NodeGroup, SimSettings};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Affinity {
+    // Given in the config, row and column `i` being the topic `keys[i]`.
+    // Topics that aren't listed have no affinity
+    Matrix {
+        keys: Vec<String>,
+        values: Vec<Vec<f32>>,
+    },
+    // How closely the topics' post counts rise and fall together, from the
+    // correlation between them
+    CoOccurrence,
+}
+
+impl Affinity {
+    /// The affinity between every pair of groups, from -1 to 1.
+    pub fn matrix(&self, groups: &[NodeGroup]) -> Vec<Vec<f32>> {
+        match self {
+            Affinity::Matrix { keys, values } => {
+                let index = |group: &NodeGroup| keys.iter().position(|k| *k == group.label);
+                let indices = groups.iter().map(index).collect::<Vec<Option<usize>>>();
+                indices
+                    .iter()
+                    .map(|a| {
+                        indices
+                            .iter()
+                            .map(|b| match (a, b) {
+                                (Some(a), Some(b)) => values
+                                    .get(*a)
+                                    .and_then(|row| row.get(*b))
+                                    .cloned()
+                                    .unwrap_or(0.0),
+                                _ => 0.0,
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+            Affinity::CoOccurrence => groups
+                .iter()
+                .map(|a| {
+                    groups
+                        .iter()
+                        .map(|b| correlation(&a.display_values, &b.display_values))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The strength `attract_nodes` uses between each pair of groups. A parent
+/// and its children are as friendly as a group is with itself, so they can
+/// overlap.
+pub fn attract_strengths(
+    groups: &[NodeGroup],
+    parents: &[Option<usize>],
+    settings: &SimSettings,
+) -> Vec<Vec<f32>> {
+    let affinities = settings.affinity.as_ref().map(|a| a.matrix(groups));
+
+    (0..groups.len())
+        .map(|g| {
+            (0..groups.len())
+                .map(|og| {
+                    if groups[g].id == groups[og].id || related(parents, g, og) {
+                        return settings.node_attract_strength_friendly;
+                    }
+                    let affinity = affinities.as_ref().map_or(0.0, |m| m[g][og]);
+                    settings.node_attract_strength + affinity * settings.affinity_strength
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Pearson correlation, 0 if either doesn't change
+fn correlation(a: &[usize], b: &[usize]) -> f32 {
+    let n = a.len().min(b.len());
+    if n == 0 {
+        return 0.0;
+    }
+    let mean = |v: &[usize]| v[..n].iter().sum::<usize>() as f32 / n as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for i in 0..n {
+        let (da, db) = (a[i] as f32 - mean_a, b[i] as f32 - mean_b);
+        covariance += da * db;
+        variance_a += da * da;
+        variance_b += db * db;
+    }
+
+    if variance_a == 0.0 || variance_b == 0.0 {
+        0.0
+    } else {
+        covariance / (variance_a * variance_b).sqrt()
+    }
+}
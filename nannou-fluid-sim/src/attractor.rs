@@ -0,0 +1,140 @@
+// Points the groups are pulled towards, or pushed away from.
+use crate::NodeGroup;
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Target {
+    // A fixed point in window space
+    Point { x: f32, y: f32 },
+    // Each group's own place on the map, or the centre if it hasn't got one
+    Anchor,
+    // Moving along a path in window space, looping every `seconds`
+    Path { points: Vec<[f32; 2]>, seconds: f32 },
+    // Somewhere between two points, further along the bigger the group is
+    // compared to the biggest group
+    Data { from: [f32; 2], to: [f32; 2] },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Falloff {
+    // The curve the nodes attract each other with, stronger further away
+    Shaped,
+    // The same strength at any distance
+    Constant,
+    // Fading out to nothing at the radius
+    Linear,
+    // Falling off with the square of the distance past the radius
+    InverseSquare,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Attractor {
+    pub target: Target,
+    // The keys of the groups it acts on, or every group
+    pub groups: Option<Vec<String>>,
+    // Negative pulls, positive pushes
+    pub strength: f32,
+    pub falloff: Falloff,
+    // The distance the falloff is measured against, or the node radius
+    pub radius: Option<f32>,
+}
+
+impl Default for Attractor {
+    // The single pull towards the middle every group used to have
+    fn default() -> Self {
+        Attractor {
+            target: Target::Anchor,
+            groups: None,
+            strength: -3.0,
+            falloff: Falloff::Shaped,
+            radius: None,
+        }
+    }
+}
+
+impl Attractor {
+    /// Where the attractor is for a group, `time` seconds into the simulation.
+    pub fn position(&self, group: &NodeGroup, max_size: usize, time: f32) -> Vec2 {
+        match &self.target {
+            Target::Point { x, y } => vec2(*x, *y),
+            Target::Anchor => group.anchor.unwrap_or(vec2(0.0, 0.0)),
+            Target::Path { points, seconds } => along_path(points, time / seconds.max(0.001)),
+            Target::Data { from, to } => {
+                let t = group.size as f32 / max_size.max(1) as f32;
+                vec2(from[0], from[1]).lerp(vec2(to[0], to[1]), t)
+            }
+        }
+    }
+
+    pub fn acts_on(&self, group: &NodeGroup) -> bool {
+        match &self.groups {
+            Some(keys) => keys.contains(&group.label),
+            None => true,
+        }
+    }
+}
+
+/// Add the forces from every attractor onto the nodes.
+pub fn attract_groups(groups: &mut [NodeGroup], attractors: &[Attractor], time: f32) {
+    let max_size = groups.iter().map(|g| g.size).max().unwrap_or(0);
+
+    for attractor in attractors.iter() {
+        for group in groups.iter_mut() {
+            if !attractor.acts_on(group) {
+                continue;
+            }
+            let target = attractor.position(group, max_size, time);
+
+            for node in group.nodes.iter_mut() {
+                let node_vector = vec2(node.x, node.y);
+                let d = node_vector.distance(target);
+                if d == 0.0 {
+                    continue;
+                }
+                let radius = attractor.radius.unwrap_or(node.radius);
+
+                let f = match attractor.falloff {
+                    Falloff::Shaped => {
+                        let s = (d / radius).powf(1.0 / node.ramp);
+                        s * 9.0 * (1.0 / (s + 1.0) + ((s - 3.0) / 4.0)) / d
+                    }
+                    Falloff::Constant => 1.0 / d,
+                    Falloff::Linear => f32::max(0.0, 1.0 - d / radius) / d,
+                    Falloff::InverseSquare => f32::min(1.0, (radius / d).powi(2)) / d,
+                };
+                node.force += (node_vector - target) * f * attractor.strength;
+            }
+        }
+    }
+}
+
+// The point `laps` of the way around a closed path, by length
+fn along_path(points: &[[f32; 2]], laps: f32) -> Vec2 {
+    let points = points
+        .iter()
+        .map(|p| vec2(p[0], p[1]))
+        .collect::<Vec<Vec2>>();
+    if points.len() < 2 {
+        return points.first().cloned().unwrap_or(vec2(0.0, 0.0));
+    }
+
+    let segments = (0..points.len())
+        .map(|i| (points[i], points[(i + 1) % points.len()]))
+        .collect::<Vec<(Vec2, Vec2)>>();
+    let total: f32 = segments.iter().map(|(a, b)| a.distance(*b)).sum();
+    if total == 0.0 {
+        return points[0];
+    }
+
+    let mut along = laps.rem_euclid(1.0) * total;
+    for (a, b) in segments.iter() {
+        let length = a.distance(*b);
+        if along <= length && length > 0.0 {
+            return a.lerp(*b, along / length);
+        }
+        along -= length;
+    }
+    points[0]
+}
@@ -0,0 +1,233 @@
+// What happens to nodes that stray outside the area the systems live in.
+use crate::{Bounds, Node, REFERENCE_RATE};
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+// How far past a soft edge a node can go before it is pushed back at full strength
+const LENIENCE: f32 = 32.0;
+// The smallest push back, in pixels per second each reference frame
+const MIN_PUSH: f32 = 0.1 * REFERENCE_RATE;
+
+// The NSW border and coastline traced from assets/map.png, in window space
+// with the map drawn centred
+const NSW_LANDMASS: [[f32; 2]; 56] = [
+    [-362.0, 244.0],
+    [131.0, 244.0],
+    [145.0, 256.0],
+    [163.0, 276.0],
+    [213.0, 273.0],
+    [243.0, 264.0],
+    [265.0, 261.0],
+    [278.0, 228.0],
+    [303.0, 248.0],
+    [323.0, 246.0],
+    [328.0, 276.0],
+    [353.0, 286.0],
+    [383.0, 281.0],
+    [413.0, 286.0],
+    [423.0, 256.0],
+    [411.0, 206.0],
+    [388.0, 126.0],
+    [391.0, 86.0],
+    [373.0, 26.0],
+    [358.0, 11.0],
+    [358.0, -14.0],
+    [338.0, -29.0],
+    [313.0, -49.0],
+    [298.0, -69.0],
+    [283.0, -99.0],
+    [273.0, -134.0],
+    [258.0, -154.0],
+    [253.0, -174.0],
+    [243.0, -199.0],
+    [233.0, -224.0],
+    [218.0, -259.0],
+    [208.0, -279.0],
+    [208.0, -309.0],
+    [193.0, -354.0],
+    [198.0, -384.0],
+    [183.0, -394.0],
+    [83.0, -352.0],
+    [73.0, -309.0],
+    [58.0, -289.0],
+    [13.0, -304.0],
+    [-17.0, -294.0],
+    [-47.0, -299.0],
+    [-77.0, -289.0],
+    [-112.0, -294.0],
+    [-117.0, -312.0],
+    [-142.0, -299.0],
+    [-157.0, -279.0],
+    [-182.0, -262.0],
+    [-202.0, -259.0],
+    [-212.0, -219.0],
+    [-242.0, -209.0],
+    [-262.0, -219.0],
+    [-275.0, -194.0],
+    [-292.0, -182.0],
+    [-332.0, -176.0],
+    [-362.0, -176.0],
+];
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Boundary {
+    // Nodes past the edge of the repel rect are pushed gently back in
+    SoftWall,
+    // Nodes bounce off the edges of the repel rect
+    Reflect,
+    // Nodes leaving one side of the repel rect come back in the other
+    Wrap,
+    // Nodes are pushed gently back inside a circle
+    Circle { x: f32, y: f32, radius: f32 },
+    // Nodes are pushed gently back inside a polygon, in window space
+    Polygon { points: Vec<[f32; 2]> },
+    // The polygon of the NSW landmass on the map
+    Landmass,
+}
+
+impl Boundary {
+    /// The outline of a polygon mask, if this is one.
+    pub fn polygon(&self) -> Option<&[[f32; 2]]> {
+        match self {
+            Boundary::Polygon { points } => Some(points),
+            Boundary::Landmass => Some(&NSW_LANDMASS),
+            _ => None,
+        }
+    }
+}
+
+/// Keep a node inside the boundary, after it has moved for `dt` seconds.
+pub fn contain(node: &mut Node, boundary: &Boundary, rect: &Bounds, dt: f32) {
+    // The pushes were tuned per frame, like the forces
+    let frames = dt * REFERENCE_RATE;
+
+    match boundary {
+        Boundary::SoftWall => {
+            soft_axis(
+                &mut node.x,
+                &mut node.velocity.x,
+                rect.left,
+                rect.right,
+                frames,
+            );
+            soft_axis(
+                &mut node.y,
+                &mut node.velocity.y,
+                rect.bottom,
+                rect.top,
+                frames,
+            );
+        }
+        Boundary::Reflect => {
+            reflect_axis(&mut node.x, &mut node.velocity.x, rect.left, rect.right);
+            reflect_axis(&mut node.y, &mut node.velocity.y, rect.bottom, rect.top);
+        }
+        Boundary::Wrap => {
+            node.x = wrap(node.x, rect.left, rect.right);
+            node.y = wrap(node.y, rect.bottom, rect.top);
+        }
+        Boundary::Circle { x, y, radius } => {
+            let centre = vec2(*x, *y);
+            let diff = vec2(node.x, node.y) - centre;
+            let d = diff.length();
+            if d > *radius {
+                soft_push(node, diff / d, d - radius, frames);
+            }
+        }
+        Boundary::Polygon { .. } | Boundary::Landmass => {
+            let outline = match boundary.polygon() {
+                Some(outline) if outline.len() > 2 => outline,
+                _ => return,
+            };
+            let point = vec2(node.x, node.y);
+            if !contains(outline, point) {
+                let nearest = nearest_on_outline(outline, point);
+                let diff = point - nearest;
+                let d = diff.length();
+                if d > 0.0 {
+                    soft_push(node, diff / d, d, frames);
+                }
+            }
+        }
+    }
+}
+
+// Take away some of the velocity heading out, and always at least a little
+fn soft_axis(pos: &mut f32, vel: &mut f32, min: f32, max: f32, frames: f32) {
+    if *pos < min {
+        let norm = f32::min(1.0, (min - *pos) / LENIENCE);
+        *vel -= f32::min(*vel * norm, -MIN_PUSH) * frames;
+    }
+    if *pos > max {
+        let norm = f32::min(1.0, (*pos - max) / LENIENCE);
+        *vel -= f32::max(*vel * norm, MIN_PUSH) * frames;
+    }
+}
+
+// The same as `soft_axis`, along the outward `normal` of a curved edge
+fn soft_push(node: &mut Node, normal: Vec2, over: f32, frames: f32) {
+    let norm = f32::min(1.0, over / LENIENCE);
+    let outward = node.velocity.dot(normal);
+    node.velocity -= normal * f32::max(outward * norm, MIN_PUSH) * frames;
+}
+
+fn reflect_axis(pos: &mut f32, vel: &mut f32, min: f32, max: f32) {
+    if *pos < min {
+        *pos = f32::min(min + (min - *pos), max);
+        *vel = vel.abs();
+    }
+    if *pos > max {
+        *pos = f32::max(max - (*pos - max), min);
+        *vel = -vel.abs();
+    }
+}
+
+fn wrap(pos: f32, min: f32, max: f32) -> f32 {
+    let size = max - min;
+    if size <= 0.0 {
+        return pos;
+    }
+    min + (pos - min).rem_euclid(size)
+}
+
+// Even-odd rule
+fn contains(outline: &[[f32; 2]], point: Vec2) -> bool {
+    let mut inside = false;
+    let mut j = outline.len() - 1;
+    for i in 0..outline.len() {
+        let (a, b) = (to_vec(outline[i]), to_vec(outline[j]));
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+fn nearest_on_outline(outline: &[[f32; 2]], point: Vec2) -> Vec2 {
+    let mut nearest = to_vec(outline[0]);
+    let mut nearest_distance = f32::MAX;
+    for i in 0..outline.len() {
+        let a = to_vec(outline[i]);
+        let b = to_vec(outline[(i + 1) % outline.len()]);
+        let ab = b - a;
+        let t = if ab.length_squared() > 0.0 {
+            ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let candidate = a + ab * t;
+        let d = candidate.distance_squared(point);
+        if d < nearest_distance {
+            nearest_distance = d;
+            nearest = candidate;
+        }
+    }
+    nearest
+}
+
+fn to_vec(point: [f32; 2]) -> Vec2 {
+    vec2(point[0], point[1])
+}
@@ -0,0 +1,34 @@
+// How much time each value in the data covers.
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum Bucket {
+    Hour,
+    SixHours,
+    #[default]
+    Day,
+    // Starting on a Monday
+    Week,
+}
+
+impl Bucket {
+    /// The length of the bucket in seconds.
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Bucket::Hour => 60 * 60,
+            Bucket::SixHours => 6 * 60 * 60,
+            Bucket::Day => 24 * 60 * 60,
+            Bucket::Week => 7 * 24 * 60 * 60,
+        }
+    }
+
+    /// What to call one bucket, for when there's no date to show.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Bucket::Hour => "Hour",
+            Bucket::SixHours => "Period",
+            Bucket::Day => "Day",
+            Bucket::Week => "Week",
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// Reading the dates in data.json, turning bucket numbers into the times they
+// cover, and writing them out for people.
+use crate::{Bucket, NodeGroupData, NodeGroupDataItem, SimSettings};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+// What older versions of data-gen wrote, toLocaleDateString() with the slashes
+// swapped for dashes, so the order depends on the machine it ran on
+const LEGACY_FORMATS: [&str; 3] = ["%m-%d-%Y", "%d-%m-%Y", "%d.%m.%Y"];
+
+// data.json as it is on disk, before the dates are worked out
+#[derive(Deserialize)]
+pub(crate) struct RawNodeGroupData {
+    groups: Vec<NodeGroupDataItem>,
+    start_date: String,
+    end_date: String,
+    #[serde(default)]
+    bucket: Bucket,
+}
+
+impl TryFrom<RawNodeGroupData> for NodeGroupData {
+    type Error = String;
+
+    fn try_from(raw: RawNodeGroupData) -> Result<Self, String> {
+        let buckets = raw.groups.iter().map(|g| g.counts.len()).max().unwrap_or(0);
+        let (start_date, end_date) =
+            parse_date_range(&raw.start_date, &raw.end_date, raw.bucket, buckets)?;
+        Ok(NodeGroupData {
+            groups: raw.groups,
+            start_date,
+            end_date,
+            bucket: raw.bucket,
+        })
+    }
+}
+
+/// Read a date written as ISO 8601, either a date or the date of a date and
+/// time.
+pub fn parse_iso_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(text)
+                .ok()
+                .map(|d| d.naive_local().date())
+        })
+}
+
+/// Read the start and end dates of data with `buckets` counts. ISO 8601 is
+/// taken as it is. The legacy formats are ambiguous, 3-1-2021 being the 3rd of
+/// January or the 1st of March, so the reading that puts the end date where
+/// the counts say it should be wins, then any that keeps the dates in order.
+pub fn parse_date_range(
+    start: &str,
+    end: &str,
+    bucket: Bucket,
+    buckets: usize,
+) -> Result<(NaiveDate, NaiveDate), String> {
+    if let (Some(start), Some(end)) = (parse_iso_date(start), parse_iso_date(end)) {
+        return Ok((start, end));
+    }
+
+    let readings = LEGACY_FORMATS
+        .iter()
+        .filter_map(|format| {
+            let start = NaiveDate::parse_from_str(start, format).ok()?;
+            let end = NaiveDate::parse_from_str(end, format).ok()?;
+            Some((start, end))
+        })
+        .collect::<Vec<(NaiveDate, NaiveDate)>>();
+
+    let expected_end = |start| bucket_time(bucket, start, buckets.saturating_sub(1)).date();
+    readings
+        .iter()
+        .find(|(start, end)| *end == expected_end(*start))
+        .or_else(|| readings.iter().find(|(start, end)| start <= end))
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "couldn't read the dates {} and {}, use YYYY-MM-DD",
+                start, end
+            )
+        })
+}
+
+/// The start of `date`.
+pub fn midnight(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).unwrap()
+}
+
+/// The start of the bucket `time` falls in. Hours and days line up with
+/// midnight, weeks start on a Monday.
+pub fn bucket_start(bucket: Bucket, time: NaiveDateTime) -> NaiveDateTime {
+    let midnight = midnight(time.date());
+    match bucket {
+        Bucket::Hour => midnight + Duration::hours(time.hour() as i64),
+        Bucket::SixHours => midnight + Duration::hours((time.hour() / 6 * 6) as i64),
+        Bucket::Day => midnight,
+        Bucket::Week => midnight - Duration::days(time.weekday().num_days_from_monday() as i64),
+    }
+}
+
+/// When bucket `index` starts, counting from midnight on `start`.
+pub fn bucket_time(bucket: Bucket, start: NaiveDate, index: usize) -> NaiveDateTime {
+    midnight(start) + Duration::seconds(bucket.seconds() * index as i64)
+}
+
+/// A short description of bucket `index`, the date in `date_format` and the
+/// hours it covers if it's less than a day, like "Mon 1 Mar 14:00".
+pub fn bucket_label(settings: &SimSettings, index: usize) -> String {
+    let time = bucket_time(settings.bucket, settings.start_date, index);
+    let date = format_date(time, &settings.date_format);
+    match settings.bucket {
+        Bucket::Hour => format!("{} {}", date, time.format("%H:00")),
+        Bucket::SixHours => format!(
+            "{} {}-{}",
+            date,
+            time.format("%H:00"),
+            (time + Duration::hours(6)).format("%H:00")
+        ),
+        Bucket::Day => date,
+        Bucket::Week => format!("Week of {}", date),
+    }
+}
+
+/// `time` written with a strftime style `format`, or as ISO 8601 if the format
+/// can't be used. Month and day names are always in English, whatever the
+/// locale.
+pub fn format_date(time: NaiveDateTime, format: &str) -> String {
+    let valid = StrftimeItems::new(format).all(|item| !matches!(item, Item::Error));
+    if valid {
+        time.format(format).to_string()
+    } else {
+        time.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn iso_dates_are_taken_as_they_are() {
+        let range = parse_date_range("2021-03-01", "2021-03-08", Bucket::Day, 8);
+        assert_eq!(range, Ok((date(2021, 3, 1), date(2021, 3, 8))));
+
+        let range = parse_date_range(
+            "2021-03-01T00:00:00+11:00",
+            "2021-03-08T00:00:00+11:00",
+            Bucket::Day,
+            8,
+        );
+        assert_eq!(range, Ok((date(2021, 3, 1), date(2021, 3, 8))));
+    }
+
+    #[test]
+    fn us_dates_are_read_month_first() {
+        // Day first would be the 3rd of January to the 3rd of August
+        let range = parse_date_range("3-1-2021", "3-8-2021", Bucket::Day, 8);
+        assert_eq!(range, Ok((date(2021, 3, 1), date(2021, 3, 8))));
+    }
+
+    #[test]
+    fn australian_dates_are_read_day_first() {
+        let range = parse_date_range("1-3-2021", "8-3-2021", Bucket::Day, 8);
+        assert_eq!(range, Ok((date(2021, 3, 1), date(2021, 3, 8))));
+
+        let range = parse_date_range("01.03.2021", "08.03.2021", Bucket::Day, 8);
+        assert_eq!(range, Ok((date(2021, 3, 1), date(2021, 3, 8))));
+    }
+
+    #[test]
+    fn the_number_of_buckets_picks_the_reading() {
+        // Either the 1st of March to the 1st of April, or the 3rd to the 4th
+        // of January
+        let range = parse_date_range("3-1-2021", "4-1-2021", Bucket::Day, 32);
+        assert_eq!(range, Ok((date(2021, 3, 1), date(2021, 4, 1))));
+
+        let range = parse_date_range("3-1-2021", "4-1-2021", Bucket::Day, 2);
+        assert_eq!(range, Ok((date(2021, 1, 3), date(2021, 1, 4))));
+    }
+
+    #[test]
+    fn readings_that_keep_the_dates_in_order_are_the_fallback() {
+        // Neither reading ends 8 days on, and month first would end before
+        // it starts
+        let range = parse_date_range("12-1-2021", "1-2-2021", Bucket::Day, 8);
+        assert_eq!(range, Ok((date(2021, 1, 12), date(2021, 2, 1))));
+    }
+
+    #[test]
+    fn unreadable_dates_are_an_error() {
+        assert!(parse_date_range("someday", "2021-03-08", Bucket::Day, 8).is_err());
+        assert!(parse_date_range("13-13-2021", "14-13-2021", Bucket::Day, 8).is_err());
+    }
+
+    #[test]
+    fn buckets_start_on_their_boundaries() {
+        let time = date(2021, 3, 4).and_hms_opt(14, 30, 0).unwrap();
+        let at = |h| date(2021, 3, 4).and_hms_opt(h, 0, 0).unwrap();
+        assert_eq!(bucket_start(Bucket::Hour, time), at(14));
+        assert_eq!(bucket_start(Bucket::SixHours, time), at(12));
+        assert_eq!(bucket_start(Bucket::Day, time), at(0));
+        // The 4th of March 2021 was a Thursday
+        assert_eq!(bucket_start(Bucket::Week, time), midnight(date(2021, 3, 1)));
+    }
+
+    #[test]
+    fn bucket_times_count_from_midnight() {
+        let start = date(2021, 3, 1);
+        assert_eq!(bucket_time(Bucket::Day, start, 0), midnight(start));
+        assert_eq!(
+            bucket_time(Bucket::SixHours, start, 5),
+            date(2021, 3, 2).and_hms_opt(6, 0, 0).unwrap()
+        );
+        assert_eq!(
+            bucket_time(Bucket::Week, start, 2),
+            midnight(date(2021, 3, 15))
+        );
+    }
+
+    #[test]
+    fn bad_date_formats_fall_back_to_iso() {
+        let time = date(2021, 3, 1).and_hms_opt(9, 0, 0).unwrap();
+        assert_eq!(format_date(time, "%a %-d %b"), "Mon 1 Mar");
+        assert_eq!(format_date(time, "%Q"), "2021-03-01 09:00");
+    }
+}
@@ -0,0 +1,227 @@
+// Per-frame geometry export, so the weather systems can be redrawn on the web
+// rather than played back from a video.
+//
+// The timeline's coordinates are in window space as used by `view`: origin at
+// the centre of the window, y pointing up. GeoJSON is turned back into
+// [lon, lat] through `map_projection`, as the format expects.
+use crate::{get_group_colour, Model};
+use chrono::NaiveDate;
+use geo::LineString;
+use nannou::prelude::*;
+use nannou_fluid_sim::{
+    group_geometry, group_trough, Bounds, Bucket, ExportFormat, MapProjection, SimSettings,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct GroupFrame {
+    id: usize,
+    key: String,
+    parent: Option<String>,
+    value: usize,
+    colour: String,
+    label: Option<[f32; 2]>,
+    hulls: Vec<Vec<[f32; 2]>>,
+    rings: Vec<Vec<[f32; 2]>>,
+    trough: Option<TroughFrame>,
+}
+
+#[derive(Serialize)]
+struct TroughFrame {
+    line: [[f32; 2]; 2],
+    keywords: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct TimelineFrame {
+    frame: u32,
+    bucket: usize,
+    groups: Vec<GroupFrame>,
+}
+
+#[derive(Serialize)]
+pub struct Timeline {
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    bucket: Bucket,
+    frame_rate: u32,
+    width: f32,
+    height: f32,
+    frames: Vec<TimelineFrame>,
+}
+
+impl Timeline {
+    pub fn new(settings: &SimSettings, window_rect: Rect) -> Self {
+        Timeline {
+            start_date: settings.start_date,
+            end_date: settings.end_date,
+            bucket: settings.bucket,
+            frame_rate: settings.frame_rate,
+            width: window_rect.w(),
+            height: window_rect.h(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Forget the frames from `bucket` on, for when playback jumps back.
+    pub fn truncate(&mut self, bucket: usize) {
+        self.frames.retain(|f| f.bucket < bucket);
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) {
+        let file = File::create(path).unwrap();
+        serde_json::to_writer(BufWriter::new(file), self).unwrap();
+    }
+}
+
+pub(crate) fn export_frame(model: &mut Model, frame: u32, label_bounds: Bounds) {
+    let groups = frame_groups(model, label_bounds);
+
+    match model.settings.export_format {
+        Some(ExportFormat::GeoJson) => {
+            let path = format!("{}/geojson/{:0>6}.geojson", model.settings.out_dir(), frame);
+            let projection = &model.settings.map_projection;
+            write_geojson(path, frame, model.bucket, &groups, projection);
+        }
+        Some(ExportFormat::Timeline) => {
+            if let Some(timeline) = model.timeline.as_mut() {
+                timeline.frames.push(TimelineFrame {
+                    frame,
+                    bucket: model.bucket,
+                    groups,
+                });
+            }
+        }
+        None => {}
+    }
+}
+
+fn frame_groups(model: &Model, label_bounds: Bounds) -> Vec<GroupFrame> {
+    model
+        .node_groups
+        .iter()
+        .map(|group| {
+            let geometry = group_geometry(group, label_bounds);
+            GroupFrame {
+                id: group.id,
+                key: group.label.clone(),
+                parent: group.parent.clone(),
+                value: group.display_values[model.bucket],
+                colour: hex_colour(get_group_colour(group.id)),
+                label: geometry.label_pos.map(|p| [p.x, p.y]),
+                hulls: group.convex_hulls.iter().map(line_string_points).collect(),
+                rings: geometry
+                    .rings
+                    .iter()
+                    .map(|ring| ring.iter().map(|p| [p.x, p.y]).collect())
+                    .collect(),
+                trough: group_trough(group, model.bucket, &model.settings).map(|trough| {
+                    TroughFrame {
+                        line: [
+                            [trough.start.x, trough.start.y],
+                            [trough.end.x, trough.end.y],
+                        ],
+                        keywords: trough.keywords,
+                    }
+                }),
+            }
+        })
+        .collect()
+}
+
+fn write_geojson<P: AsRef<Path>>(
+    path: P,
+    frame: u32,
+    bucket: usize,
+    groups: &[GroupFrame],
+    projection: &MapProjection,
+) {
+    let lon_lat = |point: &[f32; 2]| {
+        let (lat, lon) = projection.unproject(glam::vec2(point[0], point[1]));
+        [lon, lat]
+    };
+    let line = |points: &[[f32; 2]]| points.iter().map(lon_lat).collect::<Vec<[f32; 2]>>();
+    let mut features = Vec::new();
+
+    for group in groups {
+        let properties = json!({
+            "id": group.id,
+            "key": group.key,
+            "parent": group.parent,
+            "value": group.value,
+            "colour": group.colour,
+        });
+
+        for (index, hull) in group.hulls.iter().enumerate() {
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "Polygon", "coordinates": [line(hull)] },
+                "properties": with_kind(&properties, "hull", index),
+            }));
+        }
+
+        for (index, ring) in group.rings.iter().enumerate() {
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": line(ring) },
+                "properties": with_kind(&properties, "ring", index),
+            }));
+        }
+
+        if let Some(trough) = &group.trough {
+            let mut trough_properties = with_kind(&properties, "trough", 0);
+            trough_properties["keywords"] = json!(trough.keywords);
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": line(&trough.line) },
+                "properties": trough_properties,
+            }));
+        }
+
+        if let Some(label) = &group.label {
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": lon_lat(label) },
+                "properties": with_kind(&properties, "label", 0),
+            }));
+        }
+    }
+
+    let collection = json!({
+        "type": "FeatureCollection",
+        "frame": frame,
+        "bucket": bucket,
+        "features": features,
+    });
+
+    let file = File::create(path).unwrap();
+    serde_json::to_writer(BufWriter::new(file), &collection).unwrap();
+}
+
+fn with_kind(properties: &serde_json::Value, kind: &str, index: usize) -> serde_json::Value {
+    let mut properties = properties.clone();
+    properties["kind"] = json!(kind);
+    properties["index"] = json!(index);
+    properties
+}
+
+fn line_string_points(line: &LineString<f32>) -> Vec<[f32; 2]> {
+    line.0.iter().map(|c| [c.x, c.y]).collect()
+}
+
+fn hex_colour(colour: Rgba) -> String {
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        (colour.red * 255.0).round() as u8,
+        (colour.green * 255.0).round() as u8,
+        (colour.blue * 255.0).round() as u8
+    )
+}
@@ -0,0 +1,348 @@
+// A port of data-gen: fetches a week of topics from the Social Media Archive
+// activities API and writes them out as data.json.
+//
+//     cargo run --release -- fetch [start date] [--out path] [--base-url url] [--cache dir] [--refresh]
+use chrono::{Duration, Local, NaiveDate};
+use nannou_fluid_sim::{Bucket, NodeGroupData, NodeGroupDataItem};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time;
+
+const DAYS_OF_DATA: i64 = 7;
+
+const API_URL: &str = "https://socialmediaarchive.sl.nsw.gov.au/api";
+const CACHE_DIR: &str = "./cache";
+// Where data-gen keeps its token
+const DOTENV_PATHS: [&str; 2] = [".env", "../data-gen/.env"];
+
+// Attempts at each request before giving up, doubling the wait between them
+const ATTEMPTS: u32 = 4;
+const FIRST_RETRY_SECONDS: u64 = 1;
+
+#[derive(Deserialize)]
+struct Activities {
+    items: Vec<Activity>,
+}
+
+#[derive(Deserialize)]
+struct Activity {
+    key: String,
+    count: f64,
+}
+
+struct Options {
+    start_date: Option<NaiveDate>,
+    out: PathBuf,
+    base_url: String,
+    cache_dir: PathBuf,
+    // Ignore cached responses and fetch everything again
+    refresh: bool,
+    // How long to wait before the first retry
+    retry_wait: time::Duration,
+}
+
+pub fn run(args: &[String], default_out: &str) -> Result<(), String> {
+    let options = parse_options(args, default_out)?;
+    let token = access_token()?;
+
+    let data = fetch(&options, &token)?;
+    let json = serde_json::to_string(&data).map_err(|e| e.to_string())?;
+    fs::write(&options.out, json)
+        .map_err(|e| format!("Couldn't write {}: {}", options.out.display(), e))?;
+    println!(
+        "Wrote {} topics to {}",
+        data.groups.len(),
+        options.out.display()
+    );
+    Ok(())
+}
+
+// Every day of the week asked for, from the cache or the API
+fn fetch(options: &Options, token: &str) -> Result<NodeGroupData, String> {
+    let (start_date, end_date) = match options.start_date {
+        Some(start_date) => (start_date, start_date + Duration::days(DAYS_OF_DATA)),
+        None => {
+            println!("No date provided. Doing week until today.");
+            let end_date = Local::now().naive_local().date();
+            (end_date - Duration::days(DAYS_OF_DATA), end_date)
+        }
+    };
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout(time::Duration::from_secs(30))
+        .build();
+
+    let mut days = Vec::new();
+    let mut date = start_date;
+    while date <= end_date {
+        days.push(fetch_day(&agent, options, token, date)?);
+        date += Duration::days(1);
+    }
+
+    Ok(build_data(days, start_date, end_date))
+}
+
+fn parse_options(args: &[String], default_out: &str) -> Result<Options, String> {
+    let mut options = Options {
+        start_date: None,
+        out: PathBuf::from(default_out),
+        base_url: std::env::var("SMA_API_URL").unwrap_or_else(|_| API_URL.to_string()),
+        cache_dir: PathBuf::from(CACHE_DIR),
+        refresh: false,
+        retry_wait: time::Duration::from_secs(FIRST_RETRY_SECONDS),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--out" => options.out = PathBuf::from(value()?),
+            "--base-url" => options.base_url = value()?,
+            "--cache" => options.cache_dir = PathBuf::from(value()?),
+            "--refresh" => options.refresh = true,
+            date if !date.starts_with("--") && options.start_date.is_none() => {
+                let start_date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("Couldn't read the date {}, use YYYY-MM-DD", date))?;
+                options.start_date = Some(start_date);
+            }
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+    Ok(options)
+}
+
+// From the environment, or a .env file like data-gen uses
+fn access_token() -> Result<String, String> {
+    if let Ok(token) = std::env::var("ACCESS_TOKEN") {
+        if !token.is_empty() {
+            return Ok(token);
+        }
+    }
+
+    for path in DOTENV_PATHS.iter() {
+        if let Ok(contents) = fs::read_to_string(path) {
+            for line in contents.lines() {
+                if let Some(token) = line.trim().strip_prefix("ACCESS_TOKEN=") {
+                    let token = token.trim_matches(|c| c == '"' || c == '\'');
+                    if !token.is_empty() {
+                        return Ok(token.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    Err("An access token is required to access the social media archive API. Please provide it via the ACCESS_TOKEN environment variable.".to_string())
+}
+
+fn fetch_day(
+    agent: &ureq::Agent,
+    options: &Options,
+    token: &str,
+    date: NaiveDate,
+) -> Result<Activities, String> {
+    let date_string = date.format("%Y-%m-%d").to_string();
+    let cache_path = options
+        .cache_dir
+        .join(format!("activities-{}.json", date_string));
+
+    // Today is still filling up, so always ask for it again
+    let cacheable = date < Local::now().naive_local().date();
+
+    let cached = fs::read_to_string(&cache_path).ok();
+    let body = match cached.filter(|_| cacheable && !options.refresh) {
+        Some(body) => {
+            println!("{} from {}", date_string, cache_path.display());
+            body
+        }
+        None => {
+            println!("{} from {}", date_string, options.base_url);
+            let url = format!("{}/activities", options.base_url.trim_end_matches('/'));
+            let body = get_with_retries(agent, &url, token, &date_string, options.retry_wait)?;
+            if cacheable {
+                fs::create_dir_all(&options.cache_dir).map_err(|e| e.to_string())?;
+                fs::write(&cache_path, &body).map_err(|e| e.to_string())?;
+            }
+            body
+        }
+    };
+
+    serde_json::from_str(&body).map_err(|e| format!("Bad response for {}: {}", date_string, e))
+}
+
+fn get_with_retries(
+    agent: &ureq::Agent,
+    url: &str,
+    token: &str,
+    date: &str,
+    retry_wait: time::Duration,
+) -> Result<String, String> {
+    // Keep the token out of anything printed
+    let redact = |e: ureq::Error| e.to_string().replace(token, "<token>");
+
+    let mut wait = retry_wait;
+    let mut attempt = 1;
+    loop {
+        let result = agent
+            .get(url)
+            .query("access_token", token)
+            .query("set", "slnsw")
+            .query("toDate", date)
+            .call();
+
+        match result {
+            Ok(response) => return response.into_string().map_err(|e| e.to_string()),
+            Err(ureq::Error::Status(401, _)) | Err(ureq::Error::Status(403, _)) => {
+                return Err("The access token was rejected".to_string());
+            }
+            // Other client errors won't get better by asking again
+            Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                return Err(format!("Request for {} failed with HTTP {}", date, code));
+            }
+            Err(e) if attempt < ATTEMPTS => {
+                println!(
+                    "Request for {} failed ({}), retrying in {}s",
+                    date,
+                    redact(e),
+                    wait.as_secs()
+                );
+                thread::sleep(wait);
+                wait *= 2;
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Request for {} failed after {} attempts: {}",
+                    date,
+                    ATTEMPTS,
+                    redact(e)
+                ));
+            }
+        }
+    }
+}
+
+fn build_data(days: Vec<Activities>, start_date: NaiveDate, end_date: NaiveDate) -> NodeGroupData {
+    let mut keys: Vec<String> = Vec::new();
+    for day in days.iter() {
+        for item in day.items.iter() {
+            if !keys.contains(&item.key) {
+                keys.push(item.key.clone());
+            }
+        }
+    }
+
+    let groups = keys
+        .into_iter()
+        .enumerate()
+        .map(|(index, key)| {
+            let counts = days
+                .iter()
+                .map(|day| match day.items.iter().find(|i| i.key == key) {
+                    Some(item) => item.count.floor() as usize,
+                    None => 0,
+                })
+                .collect();
+            NodeGroupDataItem {
+                key,
+                index,
+                counts,
+                location: None,
+                keywords: Vec::new(),
+                parent: None,
+            }
+        })
+        .collect();
+
+    NodeGroupData {
+        groups,
+        start_date,
+        end_date,
+        bucket: Bucket::Day,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::test_util::{counts, date};
+    use std::collections::HashSet;
+    use std::net::TcpListener;
+    use std::path::Path;
+
+    // The first day of the sample responses in ./fixtures
+    fn start_date() -> NaiveDate {
+        date(2021, 3, 1)
+    }
+
+    // Replay ./fixtures on a free port in the background, returning the API's URL
+    fn serve(flaky: bool) -> String {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            let mut failed_once = HashSet::new();
+            for stream in listener.incoming() {
+                let _ = stream.and_then(|s| fixtures::respond(s, &dir, flaky, &mut failed_once));
+            }
+        });
+        format!("http://127.0.0.1:{}/api", port)
+    }
+
+    fn fetch_fixtures(name: &str, flaky: bool) -> Result<NodeGroupData, String> {
+        let dir = std::env::temp_dir().join(format!(
+            "nannou-fluid-sim-fetch-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let options = Options {
+            start_date: Some(start_date()),
+            out: dir.join("data.json"),
+            base_url: serve(flaky),
+            cache_dir: dir.join("cache"),
+            refresh: true,
+            retry_wait: time::Duration::from_millis(10),
+        };
+
+        let data = fetch(&options, "test");
+        fs::remove_dir_all(&dir).ok();
+        data
+    }
+
+    fn check_week(data: &NodeGroupData) {
+        assert_eq!(data.start_date, start_date());
+        assert_eq!(data.end_date, start_date() + Duration::days(DAYS_OF_DATA));
+        assert_eq!(data.bucket, Bucket::Day);
+        assert_eq!(data.groups.len(), 10);
+        for (index, group) in data.groups.iter().enumerate() {
+            assert_eq!(group.index, index);
+            assert_eq!(group.counts.len(), 8);
+        }
+        assert_eq!(
+            counts(data, "covid19"),
+            [773, 590, 1243, 1000, 866, 1246, 1170, 584]
+        );
+        // Only in the responses for some days
+        assert_eq!(counts(data, "mardigras"), [0, 0, 0, 0, 100, 127, 136, 0]);
+    }
+
+    #[test]
+    fn fetches_a_week_from_the_fixtures() {
+        let data = fetch_fixtures("plain", false).unwrap();
+        check_week(&data);
+    }
+
+    #[test]
+    fn retries_failed_requests() {
+        let data = fetch_fixtures("flaky", true).unwrap();
+        check_week(&data);
+    }
+}
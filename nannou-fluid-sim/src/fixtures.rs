@@ -0,0 +1,125 @@
+// A stand-in for the activities API that replays recorded responses, so
+// `fetch` can be run without the network or a real token:
+//
+//     cargo run -- serve-fixtures [dir] [--port 8000] [--flaky]
+//     ACCESS_TOKEN=test cargo run -- fetch 2021-03-01 --base-url http://127.0.0.1:8000/api --refresh
+//
+// Responses are read from `<dir>/activities-<toDate>.json`, the same names
+// `fetch` caches them under, so a cache directory can be replayed too.
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+
+const FIXTURES_DIR: &str = "./fixtures";
+const PORT: u16 = 8000;
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut dir = PathBuf::from(FIXTURES_DIR);
+    let mut port = PORT;
+    // Fail the first request for each date, to exercise the retries
+    let mut flaky = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                port = args
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .ok_or("--port needs a number")?;
+            }
+            "--flaky" => flaky = true,
+            path if !path.starts_with("--") => dir = PathBuf::from(path),
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+    println!(
+        "Replaying {} on http://127.0.0.1:{}/api",
+        dir.display(),
+        port
+    );
+
+    let mut failed_once = HashSet::new();
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| respond(stream, &dir, flaky, &mut failed_once));
+        if let Err(e) = result {
+            println!("Couldn't respond: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Answer a single request on `stream` from the responses in `dir`.
+pub(crate) fn respond(
+    mut stream: TcpStream,
+    dir: &Path,
+    flaky: bool,
+    failed_once: &mut HashSet<String>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers don't matter, but have to be read
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (path, query) = match target.find('?') {
+        Some(i) => (&target[..i], &target[i + 1..]),
+        None => (target, ""),
+    };
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+    };
+
+    let (status, body) = if path != "/api/activities" {
+        (404, error_body("Not found"))
+    } else if param("access_token").unwrap_or_default().is_empty() {
+        (401, error_body("Missing access token"))
+    } else {
+        match param("toDate") {
+            Some(date) if flaky && failed_once.insert(date.clone()) => {
+                (503, error_body("Flaky on purpose"))
+            }
+            Some(date) => match fs::read_to_string(dir.join(format!("activities-{}.json", date))) {
+                Ok(body) => (200, body),
+                Err(_) => (404, error_body("No fixture for that date")),
+            },
+            None => (400, error_body("Missing toDate")),
+        }
+    };
+
+    println!("{} {}", status, path);
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        _ => "Service Unavailable",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
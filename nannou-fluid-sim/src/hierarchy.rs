@@ -0,0 +1,69 @@
+// Topics nested inside others, like smoke and RFS inside bushfire. A parent is
+// drawn as one big system around its own nodes and all of its children's.
+use crate::NodeGroup;
+use glam::{vec2, Vec2};
+
+/// The index of each group's parent, if it has one in `groups`.
+pub fn parent_indices(groups: &[NodeGroup]) -> Vec<Option<usize>> {
+    groups
+        .iter()
+        .map(|group| {
+            let parent = group.parent.as_ref()?;
+            groups
+                .iter()
+                .position(|g| g.label == *parent && g.id != group.id)
+        })
+        .collect()
+}
+
+/// Whether one of the groups is an ancestor of the other.
+pub fn related(parents: &[Option<usize>], a: usize, b: usize) -> bool {
+    ancestors(parents, a).contains(&b) || ancestors(parents, b).contains(&a)
+}
+
+/// The groups `g` sits inside of, nearest first.
+pub fn ancestors(parents: &[Option<usize>], g: usize) -> Vec<usize> {
+    let mut ancestors = Vec::new();
+    let mut current = parents[g];
+    // A topic listed as its own ancestor would otherwise go round forever
+    while let Some(parent) = current {
+        if parent == g || ancestors.contains(&parent) {
+            break;
+        }
+        ancestors.push(parent);
+        current = parents[parent];
+    }
+    ancestors
+}
+
+/// Pull every child's nodes towards the middle of its parent's nodes, with a
+/// constant force of `strength`. Negative pulls, like the attractors.
+pub fn attract_children(groups: &mut [NodeGroup], parents: &[Option<usize>], strength: f32) {
+    for (g, parent) in parents.iter().enumerate() {
+        let target = match parent.and_then(|p| centre(&groups[p])) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        for node in groups[g].nodes.iter_mut() {
+            let node_vector = vec2(node.x, node.y);
+            let d = node_vector.distance(target);
+            if d == 0.0 {
+                continue;
+            }
+            node.force += (node_vector - target) / d * strength;
+        }
+    }
+}
+
+// The mean position of the group's nodes, if it has any
+fn centre(group: &NodeGroup) -> Option<Vec2> {
+    if group.nodes.is_empty() {
+        return None;
+    }
+    let sum = group
+        .nodes
+        .iter()
+        .fold(vec2(0.0, 0.0), |sum, n| sum + vec2(n.x, n.y));
+    Some(sum / group.nodes.len() as f32)
+}
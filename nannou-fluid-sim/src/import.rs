@@ -0,0 +1,467 @@
+// Turning topic counts from spreadsheets and event streams into data.json.
+//
+//     cargo run --release -- import <file.csv or file.ndjson> [--bucket day] [--out path]
+//
+// Long CSV has a row per topic per date, `date,topic,count`. Wide CSV has a row
+// per topic and a column per date, `topic,2021-03-01,2021-03-02,...`. NDJSON
+// has an event per line, `{"date": "...", "topic": "...", "count": 3}`, where
+// the count defaults to 1 and an optional `id` catches repeated events. Counts
+// are added up into hours, 6 hours, days or weeks, depending on `--bucket`.
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use nannou_fluid_sim::{
+    bucket_start, bucket_time, midnight, Bucket, NodeGroupData, NodeGroupDataItem,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// More than this in one row is surely a mistake, and enough of them would
+// overflow the totals
+const MAX_COUNT: f64 = 1e9;
+
+#[derive(Deserialize)]
+struct Event {
+    date: String,
+    topic: String,
+    #[serde(default = "one")]
+    count: f64,
+    id: Option<serde_json::Value>,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+// Counts for each topic and bucket, gathered from whichever format
+struct Counts {
+    bucket: Bucket,
+    topics: Vec<String>,
+    counts: HashMap<(String, NaiveDateTime), usize>,
+}
+
+impl Counts {
+    fn new(bucket: Bucket) -> Self {
+        Counts {
+            bucket,
+            topics: Vec::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, topic: &str, time: NaiveDateTime, count: usize) {
+        if !self.topics.iter().any(|t| t == topic) {
+            self.topics.push(topic.to_string());
+        }
+        let start = bucket_start(self.bucket, time);
+        *self.counts.entry((topic.to_string(), start)).or_insert(0) += count;
+    }
+
+    fn into_data(self) -> Result<NodeGroupData, String> {
+        let first = self.counts.keys().map(|k| k.1).min();
+        let last = self.counts.keys().map(|k| k.1).max();
+        let (first, last) = match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err("There are no counts in the file".to_string()),
+        };
+        // The data starts at midnight, so hourly counts start from the first
+        // hour of the day
+        let start_date = bucket_start(self.bucket, first).date();
+        let buckets = (last - midnight(start_date)).num_seconds() / self.bucket.seconds() + 1;
+
+        let bucket = self.bucket;
+        let counts = &self.counts;
+        let groups = self
+            .topics
+            .iter()
+            .enumerate()
+            .map(|(index, topic)| NodeGroupDataItem {
+                key: topic.clone(),
+                index,
+                counts: (0..buckets as usize)
+                    .map(|index| {
+                        let time = bucket_time(bucket, start_date, index);
+                        counts.get(&(topic.clone(), time)).cloned().unwrap_or(0)
+                    })
+                    .collect(),
+                location: None,
+                keywords: Vec::new(),
+                parent: None,
+            })
+            .collect();
+
+        Ok(NodeGroupData {
+            groups,
+            start_date,
+            end_date: last.date(),
+            bucket,
+        })
+    }
+}
+
+pub fn run(args: &[String], default_out: &str) -> Result<(), String> {
+    let mut input = None;
+    let mut out = default_out.to_string();
+    let mut bucket = Bucket::Day;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out = args.next().cloned().ok_or("--out needs a path")?,
+            "--bucket" => {
+                bucket = match args.next().map(|b| b.as_str()) {
+                    Some("hour") => Bucket::Hour,
+                    Some("6h") => Bucket::SixHours,
+                    Some("day") => Bucket::Day,
+                    Some("week") => Bucket::Week,
+                    _ => return Err("--bucket needs one of hour, 6h, day or week".to_string()),
+                }
+            }
+            path if !path.starts_with("--") && input.is_none() => input = Some(path.to_string()),
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+    let input = input.ok_or("Give a CSV or NDJSON file to import")?;
+
+    let data = read(&input, bucket)?;
+    let json = serde_json::to_string(&data).map_err(|e| e.to_string())?;
+    fs::write(&out, json).map_err(|e| format!("Couldn't write {}: {}", out, e))?;
+    println!(
+        "Imported {} topics over {} {}s from {} to {}",
+        data.groups.len(),
+        data.groups.first().map_or(0, |g| g.counts.len()),
+        bucket.name().to_lowercase(),
+        input,
+        out
+    );
+    Ok(())
+}
+
+/// Read counts from a file into buckets, picking the format from its extension.
+pub fn read<P: AsRef<Path>>(path: P, bucket: Bucket) -> Result<NodeGroupData, String> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let counts = match extension.as_str() {
+        "csv" => read_csv(path, Counts::new(bucket)),
+        "ndjson" | "jsonl" => read_ndjson(path, Counts::new(bucket)),
+        _ => Err("Only .csv, .ndjson and .jsonl files can be imported".to_string()),
+    };
+    counts
+        .and_then(Counts::into_data)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn read_csv(path: &Path, counts: Counts) -> Result<Counts, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(|e| e.to_string())?;
+    let headers = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|h| h.to_lowercase())
+        .collect::<Vec<String>>();
+
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    match (column("date"), column("topic"), column("count")) {
+        (Some(date), Some(topic), Some(count)) => read_long_csv(reader, counts, date, topic, count),
+        _ if headers.first().map(|h| h.as_str()) == Some("topic") => {
+            read_wide_csv(reader, counts, &headers)
+        }
+        _ => Err(
+            "Expected either date,topic,count columns or a topic column then a column per day"
+                .to_string(),
+        ),
+    }
+}
+
+fn read_long_csv(
+    mut reader: csv::Reader<fs::File>,
+    mut counts: Counts,
+    date_column: usize,
+    topic_column: usize,
+    count_column: usize,
+) -> Result<Counts, String> {
+    // Where each date and topic was first seen, however the date was written
+    let mut seen: HashMap<(NaiveDateTime, String), u64> = HashMap::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let line = record.position().map_or(0, |p| p.line());
+        let field = |i: usize| record.get(i).unwrap_or("");
+
+        let (date_field, topic) = (field(date_column), field(topic_column));
+        let date = parse_date(date_field).map_err(|e| format!("line {}: {}", line, e))?;
+        if let Some(first) = seen.insert((date, topic.to_string()), line) {
+            return Err(format!(
+                "line {}: duplicate row for {} on {}, first seen on line {}",
+                line, topic, date_field, first
+            ));
+        }
+
+        let count =
+            parse_count(field(count_column)).map_err(|e| format!("line {}: {}", line, e))?;
+        counts.add(topic, date, count);
+    }
+    Ok(counts)
+}
+
+fn read_wide_csv(
+    mut reader: csv::Reader<fs::File>,
+    mut counts: Counts,
+    headers: &[String],
+) -> Result<Counts, String> {
+    let dates = headers[1..]
+        .iter()
+        .map(|h| parse_date(h).map_err(|e| format!("header: {}", e)))
+        .collect::<Result<Vec<NaiveDateTime>, String>>()?;
+    for (i, date) in dates.iter().enumerate() {
+        if dates[..i].contains(date) {
+            return Err(format!("header: {} has more than one column", date));
+        }
+    }
+
+    let mut seen: HashMap<String, u64> = HashMap::new();
+
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let line = record.position().map_or(0, |p| p.line());
+
+        let topic = record.get(0).unwrap_or("");
+        if let Some(first) = seen.insert(topic.to_string(), line) {
+            return Err(format!(
+                "line {}: duplicate row for {}, first seen on line {}",
+                line, topic, first
+            ));
+        }
+
+        for (i, date) in dates.iter().enumerate() {
+            let count = parse_count(record.get(i + 1).unwrap_or(""))
+                .map_err(|e| format!("line {}: {}", line, e))?;
+            counts.add(topic, *date, count);
+        }
+    }
+    Ok(counts)
+}
+
+fn read_ndjson(path: &Path, mut counts: Counts) -> Result<Counts, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for (i, text) in BufReader::new(file).lines().enumerate() {
+        let line = i + 1;
+        let text = text.map_err(|e| format!("line {}: {}", line, e))?;
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let event: Event =
+            serde_json::from_str(&text).map_err(|e| format!("line {}: {}", line, e))?;
+        if let Some(id) = &event.id {
+            if let Some(first) = seen.insert(id.to_string(), line) {
+                return Err(format!(
+                    "line {}: duplicate event {}, first seen on line {}",
+                    line, id, first
+                ));
+            }
+        }
+
+        let date = parse_date(&event.date).map_err(|e| format!("line {}: {}", line, e))?;
+        let count = check_count(event.count).map_err(|e| format!("line {}: {}", line, e))?;
+        counts.add(&event.topic, date, count);
+    }
+    Ok(counts)
+}
+
+// A date and time, or midnight at the start of a date
+fn parse_date(text: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(midnight(date));
+    }
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(date_time.naive_local());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"].iter() {
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(date_time);
+        }
+    }
+    Err(format!(
+        "couldn't read the date '{}', use YYYY-MM-DD or an ISO 8601 date and time",
+        text
+    ))
+}
+
+fn parse_count(text: &str) -> Result<usize, String> {
+    if text.is_empty() {
+        return Ok(0);
+    }
+    let count = text
+        .parse::<f64>()
+        .map_err(|_| format!("couldn't read the count '{}'", text))?;
+    check_count(count)
+}
+
+// The whole number of posts in `count`, if it's a believable one
+fn check_count(count: f64) -> Result<usize, String> {
+    if !count.is_finite() {
+        Err(format!("the count {} isn't a number", count))
+    } else if count < 0.0 {
+        Err(format!("negative count {}", count))
+    } else if count > MAX_COUNT {
+        Err(format!(
+            "the count {:e} is over the limit of {}",
+            count, MAX_COUNT
+        ))
+    } else {
+        Ok(count.floor() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{counts, date};
+
+    // Import `contents` as if from a file called `name`
+    fn import(name: &str, contents: &str, bucket: Bucket) -> Result<NodeGroupData, String> {
+        let dir =
+            std::env::temp_dir().join(format!("nannou-fluid-sim-import-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        let data = read(&path, bucket);
+        fs::remove_file(&path).ok();
+        data.map_err(|e| e.replacen(&format!("{}: ", path.display()), "", 1))
+    }
+
+    #[test]
+    fn reads_long_csv() {
+        let csv = "date,topic,count\n\
+                   2021-03-01,floods,3\n\
+                   2021-03-03,floods,5.7\n\
+                   2021-03-02,bushfire,2\n";
+        let data = import("long.csv", csv, Bucket::Day).unwrap();
+
+        assert_eq!(data.start_date, date(2021, 3, 1));
+        assert_eq!(data.end_date, date(2021, 3, 3));
+        let keys = data
+            .groups
+            .iter()
+            .map(|g| g.key.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(keys, ["floods", "bushfire"]);
+        assert_eq!(counts(&data, "floods"), [3, 0, 5]);
+        assert_eq!(counts(&data, "bushfire"), [0, 2, 0]);
+    }
+
+    #[test]
+    fn duplicate_long_csv_rows_are_an_error() {
+        let csv = "date,topic,count\n\
+                   2021-03-01,floods,3\n\
+                   2021-03-02,floods,1\n\
+                   2021-03-01,floods,4\n";
+        let error = import("duplicate.csv", csv, Bucket::Day).err().unwrap();
+        assert_eq!(
+            error,
+            "line 4: duplicate row for floods on 2021-03-01, first seen on line 2"
+        );
+
+        let csv = "date,topic,count\n\
+                   2021-03-01,floods,3\n\
+                   2021-03-01T00:00:00,floods,4\n";
+        let error = import("duplicate.csv", csv, Bucket::Day).err().unwrap();
+        assert_eq!(
+            error,
+            "line 3: duplicate row for floods on 2021-03-01T00:00:00, first seen on line 2"
+        );
+    }
+
+    #[test]
+    fn reads_wide_csv() {
+        let csv = "topic,2021-03-01,2021-03-02\n\
+                   floods,3,\n\
+                   bushfire,1,2\n";
+        let data = import("wide.csv", csv, Bucket::Day).unwrap();
+        assert_eq!(counts(&data, "floods"), [3, 0]);
+        assert_eq!(counts(&data, "bushfire"), [1, 2]);
+    }
+
+    #[test]
+    fn duplicate_wide_csv_rows_are_an_error() {
+        let csv = "topic,2021-03-01\nfloods,3\nfloods,1\n";
+        let error = import("duplicate-wide.csv", csv, Bucket::Day)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            "line 3: duplicate row for floods, first seen on line 2"
+        );
+    }
+
+    #[test]
+    fn adds_up_events_into_buckets() {
+        let ndjson = r#"{"date": "2021-03-01T01:00:00", "topic": "floods"}
+{"date": "2021-03-01T05:30:00", "topic": "floods", "count": 2}
+
+{"date": "2021-03-01 13:00", "topic": "floods", "id": 7}
+"#;
+        let data = import("events.ndjson", ndjson, Bucket::SixHours).unwrap();
+        assert_eq!(data.start_date, date(2021, 3, 1));
+        assert_eq!(counts(&data, "floods"), [3, 0, 1]);
+    }
+
+    #[test]
+    fn repeated_events_are_an_error() {
+        let ndjson = r#"{"date": "2021-03-01", "topic": "floods", "id": "a"}
+{"date": "2021-03-01", "topic": "floods", "id": "b"}
+{"date": "2021-03-02", "topic": "floods", "id": "a"}
+"#;
+        let error = import("repeated.ndjson", ndjson, Bucket::Day)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            r#"line 3: duplicate event "a", first seen on line 1"#
+        );
+    }
+
+    #[test]
+    fn hourly_data_starts_at_midnight() {
+        let csv = "date,topic,count\n2021-03-01T02:15:00,floods,4\n";
+        let data = import("hourly.csv", csv, Bucket::Hour).unwrap();
+        assert_eq!(counts(&data, "floods"), [0, 0, 4]);
+    }
+
+    #[test]
+    fn reads_dates_and_times() {
+        let at = |h, m| date(2021, 3, 1).and_hms_opt(h, m, 0).unwrap();
+        assert_eq!(parse_date("2021-03-01"), Ok(at(0, 0)));
+        assert_eq!(parse_date("2021-03-01T09:30:00+11:00"), Ok(at(9, 30)));
+        assert_eq!(parse_date("2021-03-01T09:30:00"), Ok(at(9, 30)));
+        assert_eq!(parse_date("2021-03-01 09:30"), Ok(at(9, 30)));
+        assert!(parse_date("1/3/2021").is_err());
+    }
+
+    #[test]
+    fn rejects_counts_that_dont_make_sense() {
+        assert_eq!(parse_count(""), Ok(0));
+        assert_eq!(parse_count("5.9"), Ok(5));
+        assert!(parse_count("-1").is_err());
+        assert!(parse_count("inf").is_err());
+        assert!(parse_count("NaN").is_err());
+        assert!(parse_count("1e12").is_err());
+        assert!(parse_count("lots").is_err());
+
+        let csv = "date,topic,count\n2021-03-01,floods,3\n2021-03-02,floods,inf\n";
+        let error = import("infinite.csv", csv, Bucket::Day).err().unwrap();
+        assert_eq!(error, "line 3: the count inf isn't a number");
+    }
+}
@@ -0,0 +1,381 @@
+// Checking data.json before a long render:
+//
+//     cargo run --release -- inspect [data.json] [--config config.json]
+//
+// Prints what's in the data and how the current settings will scale it, then
+// exits non-zero if there's anything that would spoil the render.
+use nannou_fluid_sim::{
+    bucket_label, build_groups, select_topics, set_bucket, step, Bounds, Clock, NodeGroupData,
+    SimSettings,
+};
+use serde_json::Value;
+use std::fs;
+use std::time::Instant;
+
+// Frames timed in each bucket for the render estimate
+const TIMED_FRAMES: u32 = 10;
+
+// What's wrong with the data. Errors would spoil the render, warnings might
+#[derive(Default)]
+struct Findings {
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+pub fn run(
+    args: &[String],
+    default_data: &str,
+    default_config: &str,
+    window_size: f32,
+) -> Result<(), String> {
+    let mut data_path = default_data.to_string();
+    let mut config_path = default_config.to_string();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config_path = args.next().cloned().ok_or("--config needs a path")?,
+            path if !path.starts_with("--") => data_path = path.to_string(),
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+
+    let text = fs::read_to_string(&data_path)
+        .map_err(|e| format!("Couldn't read {}: {}", data_path, e))?;
+    let data: NodeGroupData =
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", data_path, e))?;
+    let raw: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+
+    let repel_rect = Bounds::from_w_h(window_size, window_size).pad(32.0);
+    let settings = SimSettings::from_file(&config_path, repel_rect, &data)
+        .map_err(|e| format!("{}: {}", config_path, e))?;
+
+    let mut findings = check(&data, &raw);
+
+    let bucket_count = data
+        .groups
+        .iter()
+        .map(|g| g.counts.len())
+        .min()
+        .unwrap_or(0);
+    println!(
+        "{}: {} groups, {} {}s from {} to {}",
+        data_path,
+        data.groups.len(),
+        bucket_count,
+        data.bucket.name().to_lowercase(),
+        data.start_date,
+        data.end_date
+    );
+
+    // Everything after this indexes the counts, so only carry on if they line up
+    if findings.errors.is_empty() && bucket_count > 0 {
+        report_selection(&data, &settings, &mut findings.warnings);
+        report_buckets(&data, &settings, bucket_count, &mut findings.warnings);
+        estimate_render(&data, &settings, bucket_count);
+    }
+
+    for warning in findings.warnings.iter() {
+        println!("warning: {}", warning);
+    }
+    for error in findings.errors.iter() {
+        println!("error: {}", error);
+    }
+
+    if findings.errors.is_empty() {
+        println!("{} looks fine", data_path);
+        Ok(())
+    } else {
+        Err(format!(
+            "{} has {} errors",
+            data_path,
+            findings.errors.len()
+        ))
+    }
+}
+
+fn check(data: &NodeGroupData, raw: &Value) -> Findings {
+    let mut findings = Findings::default();
+    let errors = &mut findings.errors;
+    let warnings = &mut findings.warnings;
+
+    if data.groups.is_empty() {
+        errors.push("there are no groups".to_string());
+        return findings;
+    }
+
+    for (i, group) in data.groups.iter().enumerate() {
+        if data.groups[..i].iter().any(|g| g.key == group.key) {
+            errors.push(format!("the key {} is used more than once", group.key));
+        }
+        if let Some(other) = data.groups[..i].iter().find(|g| g.index == group.index) {
+            warnings.push(format!(
+                "{} and {} share the index {}, so they'll be the same colour and attract like one group",
+                other.key, group.key, group.index
+            ));
+        }
+    }
+
+    let lengths = data.groups.iter().map(|g| g.counts.len());
+    let (shortest, longest) = (lengths.clone().min().unwrap(), lengths.max().unwrap());
+    if shortest == 0 {
+        errors.push("some groups have no counts".to_string());
+    }
+    if shortest != longest {
+        for group in data.groups.iter().filter(|g| g.counts.len() != longest) {
+            errors.push(format!(
+                "{} has {} counts where others have {}",
+                group.key,
+                group.counts.len(),
+                longest
+            ));
+        }
+    }
+
+    for group in data.groups.iter() {
+        if let Some(parent) = &group.parent {
+            if *parent == group.key {
+                warnings.push(format!("{} is its own parent", group.key));
+            } else if !data.groups.iter().any(|g| g.key == *parent) {
+                warnings.push(format!(
+                    "{}'s parent {} isn't in the data, so it'll be drawn on its own",
+                    group.key, parent
+                ));
+            }
+        }
+        if !group.keywords.is_empty() && group.keywords.len() != group.counts.len() {
+            warnings.push(format!(
+                "{} has keywords for {} buckets but counts for {}",
+                group.key,
+                group.keywords.len(),
+                group.counts.len()
+            ));
+        }
+    }
+
+    // Older files carry node counts as day_values alongside the raw counts.
+    // They're worked out again from the settings now, but a mismatch means
+    // the file was put together wrong
+    let raw_groups = raw["groups"].as_array().cloned().unwrap_or_default();
+    for group in raw_groups.iter() {
+        let length = |field: &str| group[field].as_array().map(|v| v.len());
+        if let (Some(days), Some(display)) = (length("day_values"), length("display_values")) {
+            if days != display {
+                errors.push(format!(
+                    "{} has {} day_values but {} display_values",
+                    group["key"].as_str().unwrap_or("?"),
+                    days,
+                    display
+                ));
+            }
+        }
+    }
+
+    findings
+}
+
+// Which topics topic_limit leaves out or adds up into the other system
+fn report_selection(data: &NodeGroupData, settings: &SimSettings, warnings: &mut Vec<String>) {
+    for key in settings.pinned_topics.iter() {
+        if !data.groups.iter().any(|g| g.key == *key) {
+            warnings.push(format!("the pinned topic {} isn't in the data", key));
+        }
+    }
+
+    let selected = select_topics(data, settings);
+    let left_out = data
+        .groups
+        .iter()
+        .filter(|g| !selected.groups.iter().any(|s| s.key == g.key))
+        .map(|g| g.key.as_str())
+        .collect::<Vec<&str>>();
+    if left_out.is_empty() {
+        return;
+    }
+
+    let fate = match &settings.other_topic {
+        Some(other) => format!("added up into {}", other),
+        None => "left out".to_string(),
+    };
+    println!(
+        "{} systems, {} topics {}: {}",
+        selected.groups.len(),
+        left_out.len(),
+        fate,
+        left_out.join(", ")
+    );
+}
+
+// The spread of counts and nodes in each bucket, and who never shows up
+fn report_buckets(
+    data: &NodeGroupData,
+    settings: &SimSettings,
+    bucket_count: usize,
+    warnings: &mut Vec<String>,
+) {
+    let groups = build_groups(data, settings);
+
+    println!(
+        "{:<24} {:>8} {:>8} {:>10} {:>8}",
+        "bucket", "min", "max", "total", "nodes"
+    );
+    for bucket in 0..bucket_count {
+        let counts = data.groups.iter().map(|g| g.counts[bucket]);
+        let nodes: usize = groups.iter().map(|g| g.bucket_values[bucket]).sum();
+        println!(
+            "{:<24} {:>8} {:>8} {:>10} {:>8}",
+            bucket_label(settings, bucket),
+            counts.clone().min().unwrap_or(0),
+            counts.clone().max().unwrap_or(0),
+            counts.sum::<usize>(),
+            nodes
+        );
+    }
+
+    for group in groups.iter() {
+        if group.bucket_values.iter().all(|v| *v == 0) {
+            warnings.push(format!(
+                "{} gets no nodes in any bucket with the current count_curve and count_divider",
+                group.label
+            ));
+        }
+    }
+}
+
+// Times a few frames of each bucket and scales them up to the whole render.
+// Drawing and saving the frames isn't included
+fn estimate_render(data: &NodeGroupData, settings: &SimSettings, bucket_count: usize) {
+    let mut groups = build_groups(data, settings);
+    let mut clock = Clock::new();
+
+    let mut frame_seconds = Vec::new();
+    for bucket in 0..bucket_count {
+        set_bucket(&mut groups, bucket, settings);
+        let started = Instant::now();
+        for _ in 0..TIMED_FRAMES {
+            step(&mut groups, settings, &mut clock);
+        }
+        frame_seconds.push(started.elapsed().as_secs_f32() / TIMED_FRAMES as f32);
+    }
+
+    let frames_per_bucket = (settings.bucket_seconds * settings.frame_rate) as f32;
+    let settle_frames = (settings.stabilize_time * settings.frame_rate) as f32;
+    let capture = frame_seconds.iter().sum::<f32>() * frames_per_bucket;
+    let settle = frame_seconds[0] * settle_frames;
+
+    println!(
+        "{} frames captured, simulating them takes about {} plus up to {} settling",
+        frames_per_bucket as usize * bucket_count,
+        duration(capture),
+        duration(settle)
+    );
+}
+
+fn duration(seconds: f32) -> String {
+    if seconds >= 60.0 {
+        let seconds = seconds.round() as u32;
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{:.1}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_json(json: &str) -> Findings {
+        let data: NodeGroupData = serde_json::from_str(json).unwrap();
+        let raw: Value = serde_json::from_str(json).unwrap();
+        check(&data, &raw)
+    }
+
+    // data.json for 3 days with the given groups
+    fn data_json(groups: &str) -> String {
+        format!(
+            r#"{{"start_date": "2021-03-01", "end_date": "2021-03-03", "groups": [{}]}}"#,
+            groups
+        )
+    }
+
+    #[test]
+    fn good_data_has_no_findings() {
+        let findings = check_json(&data_json(
+            r#"{"key": "floods", "index": 0, "counts": [1, 2, 3]},
+               {"key": "rain", "index": 1, "counts": [0, 4, 1], "parent": "floods"}"#,
+        ));
+        assert!(findings.errors.is_empty(), "{:?}", findings.errors);
+        assert!(findings.warnings.is_empty(), "{:?}", findings.warnings);
+    }
+
+    #[test]
+    fn broken_data_is_an_error() {
+        let findings = check_json(&data_json(
+            r#"{"key": "floods", "index": 0, "counts": [1, 2, 3]},
+               {"key": "floods", "index": 1, "counts": [1, 2, 3]},
+               {"key": "rain", "index": 2, "counts": [1, 2]}"#,
+        ));
+        assert_eq!(
+            findings.errors,
+            [
+                "the key floods is used more than once",
+                "rain has 2 counts where others have 3"
+            ]
+        );
+
+        assert_eq!(check_json(&data_json("")).errors, ["there are no groups"]);
+
+        let findings = check_json(&data_json(
+            r#"{"key": "floods", "index": 0, "display_values": [1, 2, 3], "day_values": [1, 2]}"#,
+        ));
+        assert_eq!(
+            findings.errors,
+            ["floods has 2 day_values but 3 display_values"]
+        );
+    }
+
+    #[test]
+    fn suspect_data_is_a_warning() {
+        let findings = check_json(&data_json(
+            r#"{"key": "floods", "index": 0, "counts": [1, 2, 3], "parent": "floods"},
+               {"key": "rain", "index": 0, "counts": [1, 2, 3], "parent": "weather"},
+               {"key": "fire", "index": 1, "counts": [1, 2, 3], "keywords": [[]]}"#,
+        ));
+        assert!(findings.errors.is_empty(), "{:?}", findings.errors);
+        assert_eq!(
+            findings.warnings,
+            [
+                "floods and rain share the index 0, so they'll be the same colour and attract like one group",
+                "floods is its own parent",
+                "rain's parent weather isn't in the data, so it'll be drawn on its own",
+                "fire has keywords for 1 buckets but counts for 3"
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_fail_the_run() {
+        let dir =
+            std::env::temp_dir().join(format!("nannou-fluid-sim-inspect-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+        let good = data_json(r#"{"key": "floods", "index": 0, "counts": [1, 2, 3]}"#);
+        fs::write(path("good.json"), good).unwrap();
+        let bad = data_json(
+            r#"{"key": "floods", "index": 0, "counts": [1, 2, 3]},
+               {"key": "floods", "index": 1, "counts": [1, 2, 3]}"#,
+        );
+        fs::write(path("bad.json"), bad).unwrap();
+
+        let config = path("no-config.json");
+        let run_on = |name: &str| run(&[path(name)], "", &config, 800.0);
+        assert_eq!(run_on("good.json"), Ok(()));
+        assert_eq!(
+            run_on("bad.json"),
+            Err(format!("{} has 1 errors", path("bad.json")))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,863 @@
+// The simulation itself: node groups, the forces acting on them and the hulls
+// traced around them. Kept free of nannou so it can also be built for wasm.
+mod affinity;
+mod attractor;
+mod boundary;
+mod bucket;
+mod date;
+mod hierarchy;
+mod projection;
+mod resize;
+mod scale;
+mod select;
+mod settings;
+mod stability;
+mod topology;
+mod trough;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use affinity::attract_strengths;
+pub use affinity::Affinity;
+use attractor::attract_groups;
+pub use attractor::{Attractor, Falloff, Target};
+use boundary::contain;
+pub use boundary::Boundary;
+pub use bucket::Bucket;
+use date::RawNodeGroupData;
+pub use date::{
+    bucket_label, bucket_start, bucket_time, format_date, midnight, parse_date_range,
+    parse_iso_date,
+};
+use hierarchy::{ancestors, attract_children, parent_indices};
+pub use projection::{Location, MapProjection};
+use resize::{removal_indices, spawn_points};
+pub use resize::{Removal, Spawn};
+use scale::node_counts;
+pub use scale::Curve;
+pub use select::{select_topics, TopicRank};
+pub use settings::{ExportFormat, SimSettings};
+pub use stability::Stability;
+pub use topology::Topology;
+use topology::{create_connections, extend_connections, survives_removal};
+pub use trough::{group_trough, Keyword, Trough};
+
+use chrono::NaiveDate;
+use dbscan::{cluster, Classification};
+use geo::algorithm::centroid::Centroid;
+use geo::algorithm::concave_hull::ConcaveHull;
+use geo::algorithm::simplify::Simplify;
+use geo::{Coordinate, LineString, Polygon};
+use glam::{vec2, Vec2};
+use rand::distributions::uniform::SampleUniform;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Result;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+// The forces were tuned as changes in velocity per frame at 20 frames a
+// second. They are scaled by this, squared, into pixels per second squared.
+const REFERENCE_RATE: f32 = 20.0;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NodeGroupDataItem {
+    pub key: String,
+    pub index: usize,
+    // Posts in each bucket. Older files carry them as display_values,
+    // alongside day_values that were already scaled into node counts
+    #[serde(alias = "display_values")]
+    pub counts: Vec<usize>,
+    // Where on the map the topic belongs, if anywhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    // The notable words in the topic's posts for each bucket, with weights
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<Vec<Keyword>>,
+    // The key of the topic this one is part of, whose system is drawn around it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "RawNodeGroupData")]
+pub struct NodeGroupData {
+    pub groups: Vec<NodeGroupDataItem>,
+    // Written as YYYY-MM-DD, older files in whatever format data-gen's
+    // machine used still load
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    // How long each count covers, the first starting at midnight on
+    // `start_date`. Older files are all daily
+    #[serde(default)]
+    pub bucket: Bucket,
+}
+
+pub struct Node {
+    pub x: f32,
+    pub y: f32,
+    radius: f32,        // Radius of impact
+    ramp: f32,          // Influences the shape of the function
+    pub damping: f32,   // Fraction of velocity lost per second, as a rate
+    pub velocity: Vec2, // Pixels per second
+    force: Vec2,        // Accumulated over a step, cleared once integrated
+    max_velocity: f32,
+    id: usize,
+}
+
+pub struct NodeGroup {
+    pub id: usize,
+    pub size: usize,
+    pub label: String,
+    // The key of the group this one sits inside, if any
+    pub parent: Option<String>,
+    // The window position the group is pulled towards, from its location
+    pub anchor: Option<Vec2>,
+    nodes: Vec<Node>,
+    spring_connections: Vec<Spring>,
+    seconds_since_connect: f32,
+    pub convex_hulls: Vec<LineString<f32>>,
+    // DBSCAN classification of each node from the last time the hulls were traced
+    pub node_classes: Vec<NodeClass>,
+    pub bucket_values: Vec<usize>,
+    pub display_values: Vec<usize>,
+    pub keywords: Vec<Vec<Keyword>>,
+}
+
+impl NodeGroup {
+    /// A group for a topic, with `bucket_values` nodes in each bucket.
+    pub fn new(
+        data: &NodeGroupDataItem,
+        bucket_values: Vec<usize>,
+        settings: &SimSettings,
+    ) -> Self {
+        let anchor = locate(data, settings);
+        let (x, y) = match anchor {
+            Some(anchor) => (anchor.x, anchor.y),
+            None => {
+                // Spread the groups without a location around the centre
+                let index_f = data.index as f32;
+                let angle = (index_f / 11.0) * (2.0 * PI);
+                let d = random_range(150.0, 250.0);
+                (angle.sin() * d, angle.cos() * d)
+            }
+        };
+
+        let nodes = (0..bucket_values[0])
+            .map(|id| {
+                let na = random_range(0.0, 2.0 * PI);
+                let nd = random_range(5.0, 50.0);
+                let nx = x + (na.cos() * nd);
+                let ny = y + (na.sin() * nd);
+                Node::new(
+                    nx,
+                    ny,
+                    settings.node_radius,
+                    settings.node_attract_ramp,
+                    settings.node_damping,
+                    settings.node_max_velocity,
+                    id,
+                )
+            })
+            .collect::<Vec<Node>>();
+
+        let spring_connections = create_connections(&nodes, settings);
+
+        NodeGroup {
+            id: data.index,
+            label: data.key.clone(),
+            parent: data.parent.clone(),
+            anchor,
+            size: nodes.len(),
+            nodes,
+            spring_connections,
+            seconds_since_connect: 0.0,
+            convex_hulls: Vec::new(),
+            node_classes: Vec::new(),
+            bucket_values,
+            display_values: data.counts.clone(),
+            keywords: data.keywords.clone(),
+        }
+    }
+
+    pub fn set_size(&mut self, new_size: usize, settings: &SimSettings) {
+        let cur_size = self.nodes.len();
+
+        if new_size < cur_size {
+            // Keep the hub of a star, unless the whole group is going
+            let keep = if settings.spring_topology == Topology::Star && new_size > 0 {
+                Some(0)
+            } else {
+                None
+            };
+            let indices = removal_indices(
+                &self.nodes,
+                cur_size - new_size,
+                settings.node_removal,
+                keep,
+            );
+            self.remove_nodes(indices, settings);
+        } else if new_size > cur_size {
+            let points = spawn_points(
+                self,
+                new_size - cur_size,
+                settings.node_spawn,
+                settings.hull_cluster_distance,
+            );
+
+            // Add our new nodes
+            for (i, (x, y)) in points.into_iter().enumerate() {
+                let n = Node::new(
+                    x,
+                    y,
+                    settings.node_radius,
+                    settings.node_attract_ramp,
+                    settings.node_damping,
+                    settings.node_max_velocity,
+                    cur_size + i,
+                );
+                self.nodes.push(n);
+            }
+
+            if !extend_connections(
+                &mut self.spring_connections,
+                &self.nodes,
+                cur_size,
+                settings,
+            ) {
+                self.reconnect(settings);
+            }
+        }
+        self.size = new_size;
+    }
+
+    /// Remove nodes by index, keeping the springs between the nodes that are left.
+    fn remove_nodes(&mut self, mut indices: Vec<usize>, settings: &SimSettings) {
+        indices.sort_unstable();
+        indices.dedup();
+
+        // Where each node ends up once the others are gone
+        let mut new_index = (0..self.nodes.len())
+            .map(Some)
+            .collect::<Vec<Option<usize>>>();
+        for &i in indices.iter() {
+            new_index[i] = None;
+        }
+        let mut next = 0;
+        for slot in new_index.iter_mut() {
+            if slot.is_some() {
+                *slot = Some(next);
+                next += 1;
+            }
+        }
+
+        for &i in indices.iter().rev() {
+            self.nodes.remove(i);
+        }
+
+        // Realign the ids
+        for i in 0..self.nodes.len() {
+            self.nodes[i].id = i;
+        }
+
+        if survives_removal(settings.spring_topology) {
+            let springs = std::mem::take(&mut self.spring_connections);
+            self.spring_connections = springs
+                .into_iter()
+                .filter_map(
+                    |spring| match (new_index[spring.from], new_index[spring.to]) {
+                        (Some(from), Some(to)) => Some(Spring { from, to, ..spring }),
+                        _ => None,
+                    },
+                )
+                .collect();
+        } else {
+            self.reconnect(settings);
+        }
+    }
+
+    /// Rebuild the springs between the nodes.
+    pub fn reconnect(&mut self, settings: &SimSettings) {
+        self.spring_connections = create_connections(&self.nodes, settings);
+        self.seconds_since_connect = 0.0;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NodeClass {
+    Core,
+    Edge,
+    Noise,
+}
+
+impl NodeGroup {
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn springs(&self) -> &[Spring] {
+        &self.spring_connections
+    }
+
+    /// Push changed settings into the existing nodes and springs.
+    pub fn apply_settings(&mut self, settings: &SimSettings) {
+        for node in self.nodes.iter_mut() {
+            node.radius = settings.node_radius;
+            node.ramp = settings.node_attract_ramp;
+            node.damping = settings.node_damping;
+            node.max_velocity = settings.node_max_velocity;
+        }
+
+        self.reconnect(settings);
+    }
+}
+
+impl Node {
+    fn new(
+        x: f32,
+        y: f32,
+        radius: f32,
+        ramp: f32,
+        damping: f32,
+        max_velocity: f32,
+        id: usize,
+    ) -> Self {
+        Node {
+            x,
+            y,
+            radius,
+            ramp,
+            damping,
+            max_velocity,
+            velocity: vec2(0.0, 0.0),
+            force: vec2(0.0, 0.0),
+            id,
+        }
+    }
+
+    // Semi-implicit Euler: the velocity is updated from the forces first, then
+    // the position from the new velocity.
+    fn update(&mut self, dt: f32) {
+        let acceleration = self.force * REFERENCE_RATE * REFERENCE_RATE;
+        self.force = vec2(0.0, 0.0);
+
+        // Integrate the damping exactly, so it behaves the same at any dt
+        let decay = (-self.damping * dt).exp();
+        if self.damping > 0.0 {
+            self.velocity = self.velocity * decay + acceleration * ((1.0 - decay) / self.damping);
+        } else {
+            self.velocity += acceleration * dt;
+        }
+        self.velocity = self.velocity.clamp_length_max(self.max_velocity);
+
+        self.x += self.velocity.x * dt;
+        self.y += self.velocity.y * dt;
+    }
+}
+
+/// An axis aligned rectangle in window space, centred on the origin.
+#[derive(Clone, Copy, Default)]
+pub struct Bounds {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl Bounds {
+    pub fn new(left: f32, right: f32, bottom: f32, top: f32) -> Self {
+        Bounds {
+            left,
+            right,
+            bottom,
+            top,
+        }
+    }
+
+    pub fn from_w_h(w: f32, h: f32) -> Self {
+        Bounds::new(-w / 2.0, w / 2.0, -h / 2.0, h / 2.0)
+    }
+
+    pub fn pad(&self, pad: f32) -> Self {
+        Bounds::new(
+            self.left + pad,
+            self.right - pad,
+            self.bottom + pad,
+            self.top - pad,
+        )
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.left
+            && point.x <= self.right
+            && point.y >= self.bottom
+            && point.y <= self.top
+    }
+}
+
+pub struct Spring {
+    pub from: usize,
+    pub to: usize,
+    length: f32,
+    stiffness: f32,
+    damping: f32,
+}
+
+pub fn read_node_data<P: AsRef<Path>>(path: P) -> Result<NodeGroupData> {
+    let file = File::open(path).map_err(serde_json::Error::io)?;
+    let reader = BufReader::new(file);
+    let u = serde_json::from_reader(reader)?;
+    Ok(u)
+}
+
+/// Resize every group to its value for `bucket`.
+pub fn set_bucket(groups: &mut [NodeGroup], bucket: usize, settings: &SimSettings) {
+    for group in groups.iter_mut() {
+        let val = group.bucket_values[bucket];
+        group.set_size(val, settings)
+    }
+}
+
+/// A group for every topic in the data that `select_topics` keeps.
+pub fn build_groups(data: &NodeGroupData, settings: &SimSettings) -> Vec<NodeGroup> {
+    let data = &select_topics(data, settings);
+    data.groups
+        .iter()
+        .zip(node_counts(data, settings))
+        .map(|(item, bucket_values)| NodeGroup::new(item, bucket_values, settings))
+        .collect()
+}
+
+/// Replace the groups' data with `data`, matching groups by key so existing
+/// nodes stay where they are. Groups no longer in the data, or no longer
+/// selected, are dropped.
+pub fn merge_node_data(
+    groups: &mut Vec<NodeGroup>,
+    data: &NodeGroupData,
+    bucket: usize,
+    settings: &SimSettings,
+) {
+    let data = &select_topics(data, settings);
+    let mut old_groups = std::mem::take(groups);
+
+    for (item, bucket_values) in data.groups.iter().zip(node_counts(data, settings)) {
+        let group = match old_groups.iter().position(|g| g.label == item.key) {
+            Some(index) => {
+                let mut group = old_groups.swap_remove(index);
+                group.id = item.index;
+                group.parent = item.parent.clone();
+                group.anchor = locate(item, settings);
+                group.bucket_values = bucket_values;
+                group.display_values = item.counts.clone();
+                group.keywords = item.keywords.clone();
+                group
+            }
+            None => NodeGroup::new(item, bucket_values, settings),
+        };
+        groups.push(group);
+    }
+
+    set_bucket(groups, bucket, settings);
+}
+
+/// Turns the time between frames into whole physics steps of a fixed length,
+/// carrying what's left over into the next frame.
+#[derive(Default)]
+pub struct Clock {
+    leftover: f64,
+    // Seconds simulated so far
+    time: f64,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock::default()
+    }
+
+    fn ticks(&mut self, seconds: f64, dt: f64) -> u32 {
+        self.leftover += seconds;
+        // Allow a little rounding error so frame rates that divide the physics
+        // rate always get the same number of steps
+        let ticks = (self.leftover / dt + 1e-6).floor().max(0.0);
+        self.leftover -= ticks * dt;
+        ticks as u32
+    }
+}
+
+/// Advance the simulation by one frame's worth of fixed physics steps, then
+/// retrace the hulls.
+pub fn step(groups: &mut [NodeGroup], settings: &SimSettings, clock: &mut Clock) {
+    let seconds = 1.0 / settings.frame_rate.max(1) as f64;
+    step_seconds(groups, settings, clock, seconds);
+}
+
+/// Advance the simulation by `seconds` of fixed physics steps, for callers
+/// that aren't drawing at `frame_rate`, then retrace the hulls.
+pub fn step_seconds(
+    groups: &mut [NodeGroup],
+    settings: &SimSettings,
+    clock: &mut Clock,
+    seconds: f64,
+) {
+    let dt = 1.0 / settings.physics_rate.max(1) as f32;
+    let ticks = clock.ticks(seconds, dt as f64);
+    let parents = parent_indices(groups);
+    let strengths = attract_strengths(groups, &parents, settings);
+    for _ in 0..ticks {
+        tick(
+            groups,
+            settings,
+            &strengths,
+            &parents,
+            dt,
+            clock.time as f32,
+        );
+        clock.time += dt as f64;
+    }
+
+    for g in 0..groups.len() {
+        // A parent's hulls take in its children's nodes too, so they end up
+        // nested inside it. The group's own nodes go first to keep their classes
+        let descendants = (0..groups.len()).filter(|c| ancestors(&parents, *c).contains(&g));
+        let points = std::iter::once(g)
+            .chain(descendants)
+            .flat_map(|c| groups[c].nodes.iter().map(|n| vec![n.x, n.y]))
+            .collect::<Vec<Vec<f32>>>();
+
+        let (new_hulls, mut node_classes) = update_hulls(points, settings);
+        node_classes.truncate(groups[g].nodes.len());
+        groups[g].convex_hulls = new_hulls;
+        groups[g].node_classes = node_classes;
+    }
+}
+
+// One fixed step of the physics: gather the forces, then integrate them.
+fn tick(
+    groups: &mut [NodeGroup],
+    settings: &SimSettings,
+    strengths: &[Vec<f32>],
+    parents: &[Option<usize>],
+    dt: f32,
+    time: f32,
+) {
+    for (g, row) in strengths.iter().enumerate() {
+        for (og, &strength) in row.iter().enumerate() {
+            attract_nodes(groups, g, og, strength);
+        }
+    }
+
+    for node_group in groups.iter_mut() {
+        // Springs that depend on where the nodes are need to keep up with them
+        node_group.seconds_since_connect += dt;
+        if settings.spring_topology.follows_positions()
+            && settings.spring_rebuild_seconds > 0.0
+            && node_group.seconds_since_connect >= settings.spring_rebuild_seconds
+        {
+            node_group.reconnect(settings);
+        }
+
+        for connection in node_group.spring_connections.iter() {
+            // apply spring forces
+            spring(&mut node_group.nodes, connection);
+        }
+    }
+
+    attract_groups(groups, &settings.attractors, time);
+    attract_children(groups, parents, settings.parent_attract_strength);
+
+    for group in groups.iter_mut() {
+        for node in group.nodes.iter_mut() {
+            // Integrate the forces into velocity and position
+            node.update(dt);
+            contain(node, &settings.boundary, &settings.repel_rect, dt);
+        }
+    }
+}
+
+/// The group with a node nearest to `point`, if any are within `max_distance`.
+pub fn pick_group(groups: &[NodeGroup], point: Vec2, max_distance: f32) -> Option<usize> {
+    let mut picked = None;
+    let mut picked_distance = max_distance;
+    for (g, group) in groups.iter().enumerate() {
+        for node in group.nodes.iter() {
+            let d = vec2(node.x, node.y).distance(point);
+            if d < picked_distance {
+                picked_distance = d;
+                picked = Some(g);
+            }
+        }
+    }
+    picked
+}
+
+/// The window position of a group's location, if it has one we can find.
+pub fn locate(data: &NodeGroupDataItem, settings: &SimSettings) -> Option<Vec2> {
+    data.location
+        .as_ref()
+        .and_then(|location| settings.map_projection.locate(location))
+}
+
+/// Move every node of a group by `delta`, e.g. while it is dragged.
+pub fn translate_group(group: &mut NodeGroup, delta: Vec2) {
+    for node in group.nodes.iter_mut() {
+        node.x += delta.x;
+        node.y += delta.y;
+    }
+}
+
+/// Push nodes within `radius` of `centre` outwards, strongest at the centre.
+pub fn gust(groups: &mut [NodeGroup], centre: Vec2, radius: f32, strength: f32) {
+    for group in groups.iter_mut() {
+        for node in group.nodes.iter_mut() {
+            let diff = vec2(node.x, node.y) - centre;
+            let d = diff.length();
+            if d > 0.0 && d < radius {
+                node.velocity += (diff / d) * strength * REFERENCE_RATE * (1.0 - d / radius);
+            }
+        }
+    }
+}
+
+fn attract_nodes(groups: &mut [NodeGroup], g: usize, og: usize, strength: f32) {
+    for t in 0..groups[g].nodes.len() {
+        for o in 0..groups[og].nodes.len() {
+            // If we're the same group, AND the same node id, we can skip.
+            if groups[g].id == groups[og].id && groups[g].nodes[t].id == groups[og].nodes[o].id {
+                continue;
+            }
+
+            let df = attract(&groups[g].nodes[t], &groups[og].nodes[o], strength);
+
+            groups[og].nodes[o].force += df;
+        }
+    }
+}
+
+fn attract(current_node: &Node, other_node: &Node, strength: f32) -> Vec2 {
+    let current_node_vector = vec2(current_node.x, current_node.y);
+    let other_node_vector = vec2(other_node.x, other_node.y);
+    let d = current_node_vector.distance(other_node_vector);
+
+    if d > 0.0 && d < current_node.radius {
+        let s = (d / current_node.radius).powf(1.0 / current_node.ramp);
+        let f = s * 9.0 * strength * (1.0 / (s + 1.0) + ((s - 3.0) / 4.0)) / d;
+        let mut df = current_node_vector - other_node_vector;
+        df *= f;
+        df
+    } else {
+        vec2(0.0, 0.0)
+    }
+}
+
+// ------ apply forces on spring and attached nodes ------
+fn spring(nodes: &mut [Node], spring_connection: &Spring) {
+    let length = spring_connection.length;
+    let stiffness = spring_connection.stiffness;
+    let damping = spring_connection.damping;
+
+    let mut diff = vec2(nodes[spring_connection.to].x, nodes[spring_connection.to].y)
+        - vec2(
+            nodes[spring_connection.from].x,
+            nodes[spring_connection.from].y,
+        );
+    // Nodes on top of each other have no direction to be pushed apart in
+    if diff.length() == 0.0 {
+        return;
+    }
+    diff = diff.normalize();
+
+    // Deviation from true spring
+    // If longer than length, don't apply forces. 1 way spring.
+    if diff.length().abs() > length {
+        return;
+    }
+
+    diff *= length;
+    let target = vec2(
+        nodes[spring_connection.from].x,
+        nodes[spring_connection.from].y,
+    ) + diff;
+
+    let mut force = target - vec2(nodes[spring_connection.to].x, nodes[spring_connection.to].y);
+    force *= 0.5;
+    force *= stiffness;
+    force *= 1.0 - damping;
+
+    nodes[spring_connection.to].force += force;
+    force *= -1.0;
+    nodes[spring_connection.from].force += force;
+}
+
+fn update_hulls(
+    points: Vec<Vec<f32>>,
+    settings: &SimSettings,
+) -> (Vec<LineString<f32>>, Vec<NodeClass>) {
+    let clusters = cluster(
+        settings.hull_cluster_distance as f64,
+        settings.hull_cluster_min_points,
+        &points,
+    );
+
+    let node_classes = clusters
+        .iter()
+        .map(|c| match c {
+            Classification::Core(_) => NodeClass::Core,
+            Classification::Edge(_) => NodeClass::Edge,
+            Classification::Noise => NodeClass::Noise,
+        })
+        .collect::<Vec<NodeClass>>();
+
+    let mut clustered_points = Vec::<Vec<Coordinate<f32>>>::new();
+    for (point_index, cluster_def) in clusters.iter().enumerate() {
+        if let Classification::Core(cluster_id) = cluster_def {
+            if clustered_points.len() <= *cluster_id {
+                clustered_points.push(Vec::<Coordinate<f32>>::new());
+            }
+
+            let copy_point = &points[point_index];
+            clustered_points[*cluster_id].push(Coordinate {
+                x: copy_point[0],
+                y: copy_point[1],
+            });
+        }
+    }
+
+    let hulls = clustered_points
+        .iter()
+        .map(|coords| {
+            let poly = Polygon::new(LineString(coords.to_vec()), vec![]);
+            let hull = poly.concave_hull(settings.hull_concavity);
+
+            hull.exterior().simplify(&settings.hull_simplify)
+        })
+        .collect::<Vec<LineString<f32>>>();
+
+    (hulls, node_classes)
+}
+
+/// The area inside a hull, by the shoelace formula.
+pub(crate) fn hull_area(hull: &LineString<f32>) -> f32 {
+    hull.0
+        .windows(2)
+        .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+        .sum::<f32>()
+        .abs()
+        / 2.0
+}
+
+/// The isobar geometry for a single group, as drawn by `view`.
+pub struct GroupGeometry {
+    // One ring per isobar, scaled in towards the centroid of its hull
+    pub rings: Vec<Vec<Vec2>>,
+    // Centre of the biggest hull, if the label should be drawn
+    pub label_pos: Option<Vec2>,
+}
+
+pub fn group_geometry(group: &NodeGroup, label_bounds: Bounds) -> GroupGeometry {
+    let mut rings = Vec::new();
+    let mut text_pos = vec2(0.0, 0.0);
+    let mut biggest_area = 0.0;
+    let mut biggest_width = 0.0;
+
+    group.convex_hulls.iter().for_each(|hull| {
+        let mut x_min = 10000.0;
+        let mut x_max = -10000.0;
+        let mut y_min = 10000.0;
+        let mut y_max = -10000.0;
+
+        for p in hull.clone().into_iter() {
+            x_min = f32::min(x_min, p.x);
+            x_max = f32::max(x_max, p.x);
+            y_min = f32::min(y_min, p.y);
+            y_max = f32::max(y_max, p.y);
+        }
+
+        let center = hull.centroid().unwrap();
+        let center_vec = vec2(center.x(), center.y());
+        let hull_width = x_max - x_min;
+        let hull_height = y_max - y_min;
+        let area = hull_width * hull_height;
+
+        if area > biggest_area {
+            biggest_area = area;
+            biggest_width = hull_width;
+            text_pos = center_vec;
+        }
+
+        let num = if area > 160000.0 {
+            3
+        } else if area > 30000.0 {
+            2
+        } else {
+            1
+        };
+        for i in 0..num + 1 {
+            let mult = (i as f32) / (num as f32);
+            let line = hull
+                .clone()
+                .into_iter()
+                .map(|p| {
+                    let point_vec = vec2(p.x, p.y);
+                    let direction_vector = point_vec - center_vec;
+                    center_vec + (direction_vector * mult)
+                })
+                .collect::<Vec<Vec2>>();
+            rings.push(line);
+        }
+    });
+
+    // Check that the text is within the windows bounds
+    let in_bounds = label_bounds.contains(text_pos);
+    let show_label =
+        !group.convex_hulls.is_empty() && biggest_area > 500.0 && in_bounds && biggest_width > 64.0;
+
+    GroupGeometry {
+        rings,
+        label_pos: if show_label { Some(text_pos) } else { None },
+    }
+}
+
+/// A random value in `[min, max)`, or `min` if the range is empty.
+pub fn random_range<T: SampleUniform + PartialOrd + Copy>(min: T, max: T) -> T {
+    if min < max {
+        rand::thread_rng().gen_range(min..max)
+    } else {
+        min
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rates_that_divide_the_physics_rate_get_even_ticks() {
+        let mut clock = Clock::new();
+        for _ in 0..100 {
+            assert_eq!(clock.ticks(1.0 / 20.0, 1.0 / 60.0), 3);
+        }
+    }
+
+    #[test]
+    fn fractions_of_a_tick_carry_over_to_later_frames() {
+        let mut clock = Clock::new();
+        // 144 frames a second is 0.42 ticks a frame at 60 steps a second
+        let ticks = (0..3)
+            .map(|_| clock.ticks(1.0 / 144.0, 1.0 / 60.0))
+            .collect::<Vec<u32>>();
+        assert_eq!(ticks, vec![0, 0, 1]);
+
+        let mut clock = Clock::new();
+        let total: u32 = (0..144).map(|_| clock.ticks(1.0 / 144.0, 1.0 / 60.0)).sum();
+        assert_eq!(total, 60);
+    }
+
+    #[test]
+    fn long_frames_catch_up_in_one_go() {
+        let mut clock = Clock::new();
+        assert_eq!(clock.ticks(0.25, 1.0 / 60.0), 15);
+        assert_eq!(clock.ticks(0.0, 1.0 / 60.0), 0);
+    }
+}
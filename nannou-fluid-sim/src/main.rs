@@ -1,302 +1,286 @@
-use dbscan::{cluster, Classification};
-use geo::algorithm::centroid::Centroid;
-use geo::algorithm::concave_hull::ConcaveHull;
-use geo::algorithm::simplify::Simplify;
-use geo::{Coordinate, LineString, Polygon};
+mod export;
+mod fetch;
+mod fixtures;
+mod import;
+mod inspect;
+mod markers;
+mod merge;
+mod overlay;
+mod panel;
+mod reload;
+#[cfg(test)]
+mod test_util;
+
+use export::Timeline;
+use markers::Markers;
 use nannou::prelude::*;
-use serde::{Deserialize, Serialize};
-use serde_json::Result;
+use nannou_fluid_sim::{
+    bucket_label, build_groups, group_geometry, group_trough, gust, locate, merge_node_data,
+    pick_group, read_node_data, set_bucket, step, translate_group, Bounds, Clock, ExportFormat,
+    NodeGroup, NodeGroupData, SimSettings, Stability,
+};
+use panel::Panel;
+use reload::Reloader;
 use std::fs;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
-
-#[derive(Serialize, Deserialize)]
-struct NodeGroupDataItem {
-    key: String,
-    index: usize,
-    day_values: Vec<usize>,
-    display_values: Vec<usize>,
-}
+use std::time::Instant;
 
-#[derive(Serialize, Deserialize)]
-struct NodeGroupData {
-    groups: Vec<NodeGroupDataItem>,
-    start_date: String,
-    end_date: String,
-}
+const DATA_PATH: &str = "../data.json";
+const CONFIG_PATH: &str = "../config.json";
+const WINDOW_SIZE: u32 = 800;
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let result = match args.first().map(|a| a.as_str()) {
+        Some("fetch") => fetch::run(&args[1..], DATA_PATH),
+        Some("serve-fixtures") => fixtures::run(&args[1..]),
+        Some("import") => import::run(&args[1..], DATA_PATH),
+        Some("merge") | Some("slice") => merge::run(&args[1..], DATA_PATH),
+        Some("inspect") | Some("validate") => {
+            inspect::run(&args[1..], DATA_PATH, CONFIG_PATH, WINDOW_SIZE as f32)
+        }
+        _ => {
+            nannou::app(model).update(update).run();
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
 
-struct Node {
-    pub x: f32,
-    pub y: f32,
-    min_x: f32,
-    max_x: f32,
-    min_y: f32,
-    max_y: f32,
-    radius: f32, // Radius of impact
-    ramp: f32,   // Influences the shape of the function
-    pub damping: f32,
-    pub velocity: Vector2,
-    max_velocity: f32,
-    id: usize,
+struct Model {
+    node_groups: Vec<NodeGroup>,
+    clock: Clock,
+    // Options
+    bucket: usize,
+    settings: SimSettings,
+    frame: u32,
+    // The frame capture started on, once the sim has settled
+    stab_frames: Option<u32>,
+    stability: Stability,
+    map_texture: wgpu::Texture,
+    display_font: nannou::text::Font,
+    timeline: Option<Timeline>,
+    markers: Markers,
+    data: NodeGroupData,
+    // Playback
+    paused: bool,
+    step_once: bool,
+    speed: f32,
+    step_budget: f32,
+    // Whether the sim moved on this update, so there's a new frame to capture
+    stepped: bool,
+    capture: bool,
+    drag: Option<Drag>,
+    gust: Option<Gust>,
+    panel: Panel,
+    overlays: Overlays,
+    step_millis: f32,
+    reloader: Reloader,
 }
 
-struct NodeGroup {
-    pub id: usize,
-    pub size: usize,
-    pub label: String,
-    nodes: Vec<Node>,
-    spring_connections: Vec<Spring>,
-    pub convex_hulls: Vec<LineString<f32>>,
-    pub day_values: Vec<usize>,
-    pub display_values: Vec<usize>,
+struct Drag {
+    group: usize,
+    last: Point2,
 }
 
-impl NodeGroup {
-    fn new(data: &NodeGroupDataItem, settings: &SimSettings) -> Self {
-        let index_f: f32 = cast(data.index).unwrap();
-        // TODO: get from settings
-        let angle = (index_f / 11.0) * (2.0 * PI);
-
-        let d = random_range(150.0, 250.0);
-        let y = angle.cos() * d;
-        let x = angle.sin() * d;
-
-        let nodes = (0..data.day_values[0])
-            .map(|id| {
-                let na = random_range(0.0, 2.0 * PI);
-                let nd = random_range(5.0, 50.0);
-                let nx = x + (na.cos() * nd);
-                let ny = y + (na.sin() * nd);
-                Node::new(
-                    nx,
-                    ny,
-                    settings.repel_rect.left(),
-                    settings.repel_rect.right(),
-                    settings.repel_rect.bottom(),
-                    settings.repel_rect.top(),
-                    settings.node_radius,
-                    settings.node_attract_ramp,
-                    settings.node_attract_damping,
-                    settings.node_max_velocity,
-                    id,
-                )
-            })
-            .collect::<Vec<Node>>();
+struct Gust {
+    centre: Point2,
+    frames_left: u32,
+}
 
-        let spring_connections = create_connections(
-            nodes.len(),
-            settings.spring_length,
-            settings.spring_stiffness,
-            settings.spring_damping,
-        );
+// How long a released gust keeps pushing, in frames
+const GUST_FRAMES: u32 = 20;
+const GUST_RADIUS: f32 = 200.0;
+const GUST_STRENGTH: f32 = 2.0;
 
-        NodeGroup {
-            id: data.index,
-            label: data.key.clone(),
-            size: nodes.len(),
-            nodes,
-            spring_connections,
-            convex_hulls: Vec::new(),
-            day_values: data.day_values.clone(),
-            display_values: data.display_values.clone(),
-        }
+impl Model {
+    fn set_bucket(&mut self, bucket: usize) {
+        self.bucket = bucket;
+        set_bucket(&mut self.node_groups, bucket, &self.settings);
+    }
+
+    fn bucket_count(&self) -> usize {
+        self.node_groups
+            .iter()
+            .map(|g| g.bucket_values.len())
+            .min()
+            .unwrap_or(0)
     }
 
-    fn set_size(&mut self, new_size: usize, settings: &SimSettings) {
-        let cur_size: i64 = cast(self.size).unwrap();
-        let new_size_i: i64 = cast(new_size).unwrap();
-        let mut delta = cur_size - new_size_i;
-        if delta < 0 {
-            delta = -delta;
+    /// Jump straight to the start of `bucket`, keeping the frame count in step.
+    /// Only while previewing, the frames jumped over would leave a hole in a
+    /// capture.
+    fn jump_to_bucket(&mut self, bucket: usize) {
+        if self.capture {
+            println!("Turn capturing off with C before jumping between buckets");
+            return;
         }
+        let bucket = bucket.min(self.bucket_count().saturating_sub(1));
+        // Jumping around counts as having settled
+        let stab_frames = *self.stab_frames.get_or_insert(self.frame);
 
-        if new_size < self.size {
-            for _ in 0..delta {
-                let index = random_range(0, self.nodes.len());
-                self.nodes.remove(index);
-            }
-        } else {
-            // Realign the ids
-            for i in 0..self.nodes.len() {
-                self.nodes[i].id = i;
+        self.set_bucket(bucket);
+        self.frame = stab_frames + (bucket as u32) * self.frames_per_bucket();
+
+        // Going back would otherwise record the same buckets twice
+        self.markers.truncate(bucket);
+        if let Some(timeline) = self.timeline.as_mut() {
+            timeline.truncate(bucket);
+        }
+    }
+
+    fn frames_per_bucket(&self) -> u32 {
+        self.settings.bucket_seconds * self.settings.frame_rate
+    }
+
+    /// Merge freshly loaded data into the running groups.
+    fn reload_data(&mut self) {
+        let data = match read_node_data(DATA_PATH) {
+            Ok(data) => data,
+            Err(e) => {
+                println!("Couldn't reload {}: {}", DATA_PATH, e);
+                return;
             }
+        };
+
+        self.settings.set_data(&data);
+        fs::create_dir_all(self.settings.out_dir()).unwrap();
+
+        let bucket_count = data
+            .groups
+            .iter()
+            .map(|g| g.counts.len())
+            .min()
+            .unwrap_or(0);
+        self.bucket = self.bucket.min(bucket_count.saturating_sub(1));
+
+        warn_unlocated(&data, &self.settings);
+        merge_node_data(&mut self.node_groups, &data, self.bucket, &self.settings);
+        // The groups may have been reordered or dropped under the mouse
+        self.drag = None;
+        self.data = data;
+        println!("Reloaded {}", DATA_PATH);
+    }
 
-            let base_id: i64 = cast(self.nodes.len()).unwrap();
-
-            // Pick a random node and pile them on here
-            // if starting from 0 do from a random spot
-            let target_index = if self.nodes.len() > 0 {
-                random_range(0, self.nodes.len())
-            } else {
-                0
-            };
-
-            let x = if self.size > 0 {
-                self.nodes[target_index].x
-            } else {
-                random_range(-200.0, 200.0)
-            };
-
-            let y = if self.size > 0 {
-                self.nodes[target_index].y
-            } else {
-                random_range(-200.0, 200.0)
-            };
-
-            // Add our new nodes
-            for i in 0..delta {
-                let id = base_id + i;
-                let id_size: usize = cast(id).unwrap();
-                let n = Node::new(
-                    x,
-                    y,
-                    settings.repel_rect.left(),
-                    settings.repel_rect.right(),
-                    settings.repel_rect.bottom(),
-                    settings.repel_rect.top(),
-                    settings.node_radius,
-                    settings.node_attract_ramp,
-                    settings.node_attract_damping,
-                    settings.node_max_velocity,
-                    id_size,
+    /// Apply a changed config file to the running simulation.
+    fn reload_settings(&mut self) {
+        let settings = SimSettings::from_file(CONFIG_PATH, self.settings.repel_rect, &self.data);
+
+        match settings {
+            // Nothing to do for our own save, or a file that was only touched
+            Ok(settings)
+                if serde_json::to_value(&settings).ok()
+                    == serde_json::to_value(&self.settings).ok() => {}
+            Ok(settings) => {
+                self.settings = settings;
+                // out_dir_format may have changed
+                fs::create_dir_all(self.settings.out_dir()).unwrap();
+                for group in self.node_groups.iter_mut() {
+                    group.apply_settings(&self.settings);
+                }
+                // The curve turning counts into nodes may have changed
+                merge_node_data(
+                    &mut self.node_groups,
+                    &self.data,
+                    self.bucket,
+                    &self.settings,
                 );
-                self.nodes.push(n);
+                // topic_limit may have changed which groups there are
+                self.drag = None;
+                println!("Reloaded {}", CONFIG_PATH);
             }
+            Err(e) => println!("Couldn't reload {}: {}", CONFIG_PATH, e),
         }
-        self.size = new_size;
-        self.spring_connections = create_connections(
-            self.nodes.len(),
-            settings.spring_length,
-            settings.spring_stiffness,
-            settings.spring_damping,
-        )
     }
-}
 
-impl Node {
-    fn new(
-        x: f32,
-        y: f32,
-        min_x: f32,
-        max_x: f32,
-        min_y: f32,
-        max_y: f32,
-        radius: f32,
-        ramp: f32,
-        damping: f32,
-        max_velocity: f32,
-        id: usize,
-    ) -> Self {
-        Node {
-            x,
-            y,
-            min_x,
-            max_x,
-            min_y,
-            max_y,
-            radius,
-            ramp,
-            damping,
-            max_velocity,
-            velocity: vec2(0.0, 0.0),
-            id,
+    /// Write the current settings to the config file.
+    fn save_settings(&self) {
+        match self.settings.write(CONFIG_PATH) {
+            Ok(()) => println!("Saved {}", CONFIG_PATH),
+            Err(e) => println!("Couldn't save {}: {}", CONFIG_PATH, e),
         }
     }
 
-    fn update(&mut self) {
-        self.velocity = self.velocity.limit_magnitude(self.max_velocity);
-
-        self.x += self.velocity.x;
-        self.y += self.velocity.y;
-
-        let lenience = 32.0;
-
-        if self.x < self.min_x {
-            let amt_over = self.x - self.min_x;
-            let norm = f32::min(1.0, -(amt_over / lenience));
-            let vel_delta = f32::max(self.velocity.x * norm, -0.1);
-            self.velocity.x -= vel_delta;
-        }
-        if self.x > self.max_x {
-            let amt_over = self.x - self.max_x;
-            let norm = f32::min(1.0, amt_over / lenience);
-            let vel_delta = f32::max(self.velocity.x * norm, 0.1);
-            self.velocity.x -= vel_delta;
+    /// Rebuild the groups from the data and start again from the first frame.
+    fn restart(&mut self) {
+        self.node_groups = build_groups(&self.data, &self.settings);
+        self.drag = None;
+        self.clock = Clock::new();
+        self.bucket = 0;
+        self.frame = 0;
+        self.stab_frames = None;
+        self.stability = Stability::new();
+        self.markers = Markers::new(&self.settings);
+        if let Some(timeline) = self.timeline.as_mut() {
+            timeline.clear();
         }
+    }
 
-        if self.y < self.min_y {
-            let amt_over = self.y - self.min_y;
-            let norm = f32::min(1.0, -(amt_over / lenience));
-            let vel_delta = f32::max(self.velocity.x * norm, 0.1);
-            self.velocity.y -= vel_delta;
-        }
-        if self.y > self.max_y {
-            let amt_over = self.y - self.max_y;
-            let norm = f32::min(1.0, amt_over / lenience);
-            let vel_delta = f32::max(self.velocity.x * norm, 0.1);
-            self.velocity.y -= vel_delta;
-        }
+    /// The first and last frames of the captured section of the render, once
+    /// the sim has settled.
+    fn capture_range(&self) -> Option<(u32, u32)> {
+        let frames = self.frames_per_bucket() * self.bucket_count() as u32;
+        self.stab_frames
+            .map(|stab_frames| (stab_frames, stab_frames + frames))
+    }
 
-        self.velocity *= 1.0 - self.damping;
+    /// The output frame number if the current frame is being captured.
+    fn capture_frame(&self) -> Option<u32> {
+        match self.capture_range() {
+            Some((stab_frames, end_frame))
+                if self.capture && self.frame > stab_frames && self.frame < end_frame =>
+            {
+                Some(self.frame - stab_frames)
+            }
+            _ => None,
+        }
     }
-}
 
-struct Model {
-    node_groups: Vec<NodeGroup>,
-    // Options
-    day: usize,
-    settings: SimSettings,
-    frame: u32,
-    map_texture: wgpu::Texture,
-    display_font: nannou::text::Font,
-}
+    /// Start capturing once the sim has settled, or has had long enough to.
+    /// The measures are kept up to date for the stats overlay after that.
+    fn check_settled(&mut self) {
+        let settled = self.stability.update(&self.node_groups, &self.settings);
+        if self.stab_frames.is_some() {
+            return;
+        }
 
-impl Model {
-    fn set_day(&mut self, day: usize) {
-        self.day = day;
+        let settings = &self.settings;
+        let seconds = self.frame as f32 / settings.frame_rate as f32;
+        let energy = self.stability.kinetic_energy;
+        let hull_change = self.stability.hull_change;
 
-        for g in 0..self.node_groups.len() {
-            let val = self.node_groups[g].day_values[day];
-            self.node_groups[g].set_size(val, &self.settings)
+        if settings.stabilize_auto && settled {
+            println!(
+                "Settled after {:.1}s, energy {:.2}, hull change {:.4}",
+                seconds, energy, hull_change
+            );
+            self.stab_frames = Some(self.frame);
+        } else if self.frame >= settings.stabilize_time * settings.frame_rate {
+            if settings.stabilize_auto {
+                println!(
+                    "Not settled after {:.1}s, capturing anyway. Energy {:.2}, hull change {:.4}",
+                    seconds, energy, hull_change
+                );
+            }
+            self.stab_frames = Some(self.frame);
+        } else if settings.stabilize_auto && self.frame % settings.frame_rate == 0 {
+            println!(
+                "Settling {:.0}s, energy {:.2}, hull change {:.4}",
+                seconds, energy, hull_change
+            );
         }
     }
 }
 
-struct SimSettings {
-    node_radius: f32,
-    node_max_velocity: f32,
-    node_attract_ramp: f32,
-    node_attract_strength: f32,
-    node_attract_strength_friendly: f32,
-    node_attract_damping: f32,
-    spring_length: f32,
-    spring_stiffness: f32,
-    spring_damping: f32,
-    repel_rect: Rect,
-    frame_rate: u32,
-    day_seconds: u32,
-    stabilize_time: u32,
-    start_date: String,
-    end_date: String,
-}
-
-struct Spring {
-    from: usize,
-    to: usize,
-    length: f32,
-    stiffness: f32,
-    damping: f32,
-}
-
 fn model(app: &App) -> Model {
-    app.new_window()
-        .size(800, 800)
+    let window = app
+        .new_window()
+        .size(WINDOW_SIZE, WINDOW_SIZE)
         .view(view)
+        .mouse_pressed(mouse_pressed)
         .mouse_released(mouse_released)
         .key_pressed(key_pressed)
         .build()
@@ -310,259 +294,193 @@ fn model(app: &App) -> Model {
     let font_path = assets.join("VCR_OSD_MONO_1.001.ttf");
     let display_font: nannou::text::Font = nannou::text::font::from_file(font_path).unwrap();
 
-    let node_group_data = read_node_data("../data.json").unwrap();
-
-    let settings = SimSettings {
-        node_radius: 120.0,
-        node_attract_ramp: 1.4,
-        node_attract_strength: -1.5,
-        node_attract_strength_friendly: -1.0,
-        node_attract_damping: 0.9,
-        node_max_velocity: 2.0,
-        spring_length: 120.0 * 1.2,
-        spring_stiffness: 4.3,
-        spring_damping: 0.9,
-        repel_rect: app.window_rect().pad(32.0),
-        frame_rate: 20,
-        day_seconds: 5,
-        stabilize_time: 10,
-        start_date: node_group_data.start_date,
-        end_date: node_group_data.end_date,
+    let node_group_data = read_node_data(DATA_PATH).unwrap();
+
+    let window_rect = app.window_rect();
+    let repel_rect = window_rect.pad(32.0);
+    let settings = SimSettings::from_file(
+        CONFIG_PATH,
+        Bounds::new(
+            repel_rect.left(),
+            repel_rect.right(),
+            repel_rect.bottom(),
+            repel_rect.top(),
+        ),
+        &node_group_data,
+    )
+    .unwrap();
+    warn_unlocated(&node_group_data, &settings);
+
+    fs::create_dir_all(settings.out_dir()).unwrap();
+    if settings.export_format == Some(ExportFormat::GeoJson) {
+        fs::create_dir_all(format!("{}/geojson", settings.out_dir())).unwrap();
+    }
+
+    let timeline = match settings.export_format {
+        Some(ExportFormat::Timeline) => Some(Timeline::new(&settings, window_rect)),
+        _ => None,
     };
 
-    fs::create_dir_all(format!("./out/{}", settings.start_date)).unwrap();
+    let markers = Markers::new(&settings);
 
-    let node_groups = node_group_data
-        .groups
-        .iter()
-        .map(|data| NodeGroup::new(&data, &settings))
-        .collect();
+    let node_groups = build_groups(&node_group_data, &settings);
 
     Model {
         // Config
         settings,
         // Scene
         node_groups,
-        day: 0,
+        clock: Clock::new(),
+        bucket: 0,
         frame: 0,
+        stab_frames: None,
+        stability: Stability::new(),
         map_texture,
         display_font,
+        timeline,
+        markers,
+        data: node_group_data,
+        paused: false,
+        step_once: false,
+        speed: 1.0,
+        step_budget: 0.0,
+        stepped: false,
+        capture: true,
+        drag: None,
+        gust: None,
+        panel: Panel::new(app, window),
+        overlays: Overlays::new(),
+        step_millis: 0.0,
+        reloader: Reloader::new(DATA_PATH, CONFIG_PATH),
     }
 }
 
-fn read_node_data<P: AsRef<Path>>(path: P) -> Result<NodeGroupData> {
-    let file = File::open(path).unwrap();
-    let reader = BufReader::new(file);
-    let u = serde_json::from_reader(reader)?;
-    Ok(u)
-}
-
-fn create_connections(
-    group_size: usize,
-    spring_length: f32,
-    spring_stiffness: f32,
-    spring_damping: f32,
-) -> Vec<Spring> {
-    let group_start = 0;
-    (1..group_size)
-        .map(|j| {
-            // let buddy = random_range(group_start, group_start + group_size);
-            Spring {
-                from: group_start,
-                to: group_start + j,
-                length: spring_length,
-                stiffness: spring_stiffness,
-                damping: spring_damping,
-            }
-        })
-        .collect::<Vec<Spring>>()
-}
+fn update(app: &App, model: &mut Model, _update: Update) {
+    let changes = model.reloader.poll();
+    if changes.data {
+        model.reload_data();
+    }
+    if changes.config {
+        model.reload_settings();
+    }
 
-fn gravity(groups: &mut Vec<NodeGroup>) {
-    let target = vec2(0.0, 0.0);
-    let strength = -3.0;
-    for g in 0..groups.len() {
-        for n in 0..groups[g].nodes.len() {
-            let current_node = &groups[g].nodes[n];
-            let current_node_vector = vec2(current_node.x, current_node.y);
-            let d = current_node_vector.distance(target);
-            let s = (d / current_node.radius).powf(1.0 / current_node.ramp);
-            let f = s * 9.0 * strength * (1.0 / (s + 1.0) + ((s - 3.0) / 4.0)) / d;
-            let df = (current_node_vector - target) * f;
-
-            groups[g].nodes[n].velocity += df;
+    let panel_events = model.panel.update(&mut model.settings);
+    if panel_events.changed {
+        for group in model.node_groups.iter_mut() {
+            group.apply_settings(&model.settings);
         }
     }
-}
+    if panel_events.save {
+        model.save_settings();
+    }
 
-fn attract_nodes(
-    groups: &mut Vec<NodeGroup>,
-    g: usize,
-    og: usize,
-    friendly_strength: f32,
-    strength: f32,
-) {
-    let strength = if groups[g].id == groups[og].id {
-        friendly_strength
+    let mouse = app.mouse.position();
+    if let Some(drag) = model.drag.as_mut() {
+        let delta = mouse - drag.last;
+        drag.last = mouse;
+        translate_group(
+            &mut model.node_groups[drag.group],
+            glam::vec2(delta.x, delta.y),
+        );
+    }
+
+    // Captures need every frame, so the speed only applies to previews
+    let steps = if model.paused {
+        if model.step_once {
+            1
+        } else {
+            0
+        }
+    } else if model.capture {
+        1
     } else {
-        strength
+        model.step_budget += model.speed;
+        let steps = model.step_budget.floor();
+        model.step_budget -= steps;
+        steps as u32
     };
+    model.step_once = false;
+    model.stepped = steps > 0;
 
-    for t in 0..groups[g].nodes.len() {
-        for o in 0..groups[og].nodes.len() {
-            // If we're the same group, AND the same node id, we can skip.
-            if groups[g].id == groups[og].id && groups[g].nodes[t].id == groups[og].nodes[o].id {
-                continue;
-            }
-
-            let df = attract(&groups[g].nodes[t], &groups[og].nodes[o], strength);
-
-            groups[og].nodes[o].velocity += df;
-        }
+    let start = Instant::now();
+    for _ in 0..steps {
+        advance(app, model);
     }
-}
-
-fn attract(current_node: &Node, other_node: &Node, strength: f32) -> Vector2 {
-    let current_node_vector = vec2(current_node.x, current_node.y);
-    let other_node_vector = vec2(other_node.x, other_node.y);
-    let d = current_node_vector.distance(other_node_vector);
-
-    if d > 0.0 && d < current_node.radius {
-        let s = (d / current_node.radius).powf(1.0 / current_node.ramp);
-        let f = s * 9.0 * strength * (1.0 / (s + 1.0) + ((s - 3.0) / 4.0)) / d;
-        let mut df = current_node_vector - other_node_vector;
-        df *= f;
-        df
-    } else {
-        vec2(0.0, 0.0)
+    if steps > 0 {
+        model.step_millis = start.elapsed().as_secs_f32() * 1000.0 / steps as f32;
     }
 }
 
-// ------ apply forces on spring and attached nodes ------
-fn spring(nodes: &mut Vec<Node>, spring_connection: &Spring) {
-    let length = spring_connection.length;
-    let stiffness = spring_connection.stiffness;
-    let damping = spring_connection.damping;
-
-    let mut diff = vec2(nodes[spring_connection.to].x, nodes[spring_connection.to].y)
-        - vec2(
-            nodes[spring_connection.from].x,
-            nodes[spring_connection.from].y,
-        );
-    diff = diff.normalize();
-
-    // Deviation from true spring
-    // If longer than length, don't apply forces. 1 way spring.
-    if abs(diff.magnitude()) > length {
+/// Run a single frame of the simulation.
+fn advance(app: &App, model: &mut Model) {
+    // The render is over, there's no more data to move on to
+    if matches!(model.capture_range(), Some((_, end_frame)) if model.frame >= end_frame) {
         return;
     }
 
-    diff *= length;
-    let target = vec2(
-        nodes[spring_connection.from].x,
-        nodes[spring_connection.from].y,
-    ) + diff;
+    if let Some(g) = model.gust.as_mut() {
+        let centre = glam::vec2(g.centre.x, g.centre.y);
+        gust(&mut model.node_groups, centre, GUST_RADIUS, GUST_STRENGTH);
+        g.frames_left -= 1;
+        if g.frames_left == 0 {
+            model.gust = None;
+        }
+    }
 
-    let mut force = target - vec2(nodes[spring_connection.to].x, nodes[spring_connection.to].y);
-    force *= 0.5;
-    force *= stiffness;
-    force *= 1.0 - damping;
+    step(&mut model.node_groups, &model.settings, &mut model.clock);
+    model.check_settled();
 
-    nodes[spring_connection.to].velocity += force;
-    force *= -1.0;
-    nodes[spring_connection.from].velocity += force;
-}
+    // Advance to the next bucket of data if required.
+    let frames_per_bucket = model.frames_per_bucket();
+
+    if let Some((stab_frames, end_frame)) = model.capture_range() {
+        if model.frame >= stab_frames {
+            let offset_frames = model.frame - stab_frames;
+            if offset_frames % frames_per_bucket == 0 {
+                let bucket: usize = cast(offset_frames / frames_per_bucket).unwrap();
 
-fn update_hulls(group: &NodeGroup) -> Vec<LineString<f32>> {
-    let points = group
-        .nodes
-        .iter()
-        .map(|n| vec![n.x, n.y])
-        .collect::<Vec<Vec<f32>>>();
-
-    let clusters = cluster(40.0, 20, &points);
-
-    let mut clustered_points = Vec::<Vec<Coordinate<f32>>>::new();
-    for (point_index, cluster_def) in clusters.iter().enumerate() {
-        match cluster_def {
-            Classification::Core(cluster_id) => {
-                if clustered_points.len() <= *cluster_id {
-                    clustered_points.push(Vec::<Coordinate<f32>>::new());
+                if model.bucket != bucket && bucket < model.bucket_count() {
+                    model.set_bucket(bucket);
                 }
 
-                let copy_point = &points[point_index];
-                clustered_points[*cluster_id].push(Coordinate {
-                    x: copy_point[0],
-                    y: copy_point[1],
-                });
+                if model.capture && model.frame < end_frame {
+                    model
+                        .markers
+                        .add(bucket, offset_frames, &model.settings, &model.node_groups);
+                }
             }
-            _ => {}
         }
     }
 
-    clustered_points
-        .iter()
-        .map(|coords| {
-            let poly = Polygon::new(LineString(coords.to_vec()), vec![]);
-            let hull = poly.concave_hull(2.0);
-
-            hull.exterior().simplify(&1.0)
-        })
-        .collect::<Vec<LineString<f32>>>()
-}
-
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    for g in 0..model.node_groups.len() {
-        for og in 0..model.node_groups.len() {
-            let ng = &mut model.node_groups;
-            attract_nodes(
-                ng,
-                g,
-                og,
-                model.settings.node_attract_strength_friendly,
-                model.settings.node_attract_strength,
-            );
-        }
-    }
+    model.frame += 1;
 
-    for g in 0..model.node_groups.len() {
-        let node_group = &mut model.node_groups[g];
-        for connection in node_group.spring_connections.iter() {
-            // apply spring forces
-            spring(&mut node_group.nodes, connection);
+    if let Some((stab_frames, end_frame)) = model.capture_range() {
+        if model.capture && model.frame == end_frame {
+            let out_dir = model.settings.out_dir();
+            let end_time = markers::video_time(end_frame - stab_frames, &model.settings);
+            model
+                .markers
+                .write_json(format!("{}/markers.json", out_dir));
+            model
+                .markers
+                .write_vtt(format!("{}/chapters.vtt", out_dir), end_time);
         }
     }
 
-    gravity(&mut model.node_groups);
-
-    for g in 0..model.node_groups.len() {
-        for i in 0..model.node_groups[g].nodes.len() {
-            // Apply velocity vector and update position
-            model.node_groups[g].nodes[i].update();
+    // Export the geometry for the frame `view` is about to capture.
+    if model.settings.export_format.is_some() {
+        let label_bounds = window_bounds(app).pad(96.0);
+        if let Some(adjusted_frame) = model.capture_frame() {
+            export::export_frame(model, adjusted_frame, label_bounds);
         }
-    }
-
-    for g in 0..model.node_groups.len() {
-        let new_hulls = update_hulls(&model.node_groups[g]);
-        model.node_groups[g].convex_hulls = new_hulls;
-    }
-
-    // Advance day if required.
-    let frames_per_day = model.settings.day_seconds * model.settings.frame_rate;
-    let stab_frames = model.settings.stabilize_time * model.settings.frame_rate;
-
-    if model.frame >= stab_frames {
-        let offset_frames = model.frame - stab_frames;
-        if offset_frames % frames_per_day == 0 {
-            let day: usize = cast(offset_frames / frames_per_day).unwrap();
 
-            if model.day != day {
-                model.set_day(day);
+        let end_frame = model.capture_range().map(|(_, end_frame)| end_frame);
+        if end_frame == Some(model.frame) {
+            if let Some(timeline) = &model.timeline {
+                timeline.write(format!("{}/timeline.json", model.settings.out_dir()));
             }
         }
     }
-
-    model.frame += 1;
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
@@ -573,69 +491,54 @@ fn view(app: &App, model: &Model, frame: Frame) {
 
     draw.texture(&model.map_texture);
 
-    for group in &model.node_groups {
-        let mut text_pos: Vector2 = Vector2 { x: 0.0, y: 0.0 };
-        let mut biggest_area = 0.0;
-        let mut biggest_width = 0.0;
-
-        group.convex_hulls.iter().for_each(|hull| {
-            let mut x_min = 10000.0;
-            let mut x_max = -10000.0;
-            let mut y_min = 10000.0;
-            let mut y_max = -10000.0;
-
-            for p in hull.clone().into_iter() {
-                x_min = f32::min(x_min, p.x);
-                x_max = f32::max(x_max, p.x);
-                y_min = f32::min(y_min, p.y);
-                y_max = f32::max(y_max, p.y);
-            }
+    // The same frame is drawn again while paused, only capture new ones
+    let capture_frame = if model.stepped {
+        model.capture_frame()
+    } else {
+        None
+    };
 
-            let center = hull.centroid().unwrap();
-            let center_vec = vec2(center.x(), center.y());
-            let hull_width = x_max - x_min;
-            let hull_height = y_max - y_min;
-            let area = hull_width * hull_height;
+    let label_bounds = window_bounds(app).pad(96.0);
 
-            if area > biggest_area {
-                biggest_area = area;
-                biggest_width = hull_width;
-                text_pos = center_vec;
-            }
+    for group in &model.node_groups {
+        let geometry = group_geometry(group, label_bounds);
+
+        for line in geometry.rings.iter() {
+            draw.polyline()
+                .color(get_group_colour(group.id))
+                .stroke_weight(model.settings.isobar_stroke_weight)
+                .join_round()
+                .points(line.iter().map(|p| vec2(p.x, p.y)));
+        }
 
-            let num = if area > 160000.0 {
-                3
-            } else if area > 30000.0 {
-                2
-            } else {
-                1
-            };
-            for i in 0..num + 1 {
-                let mult = (i as f32) / (num as f32);
-                let line = hull
-                    .clone()
-                    .into_iter()
-                    .map(|p| {
-                        let point_vec = vec2(p.x, p.y);
-                        let direction_vector = point_vec - center_vec;
-                        center_vec + (direction_vector * mult)
-                    })
-                    .collect::<Vec<Vector2>>();
-                draw.polyline()
+        if let Some(trough) = group_trough(group, model.bucket, &model.settings) {
+            for (start, end) in trough.dashes(model.settings.trough_dash) {
+                draw.line()
+                    .start(vec2(start.x, start.y))
+                    .end(vec2(end.x, end.y))
                     .color(get_group_colour(group.id))
-                    .stroke_weight(4.0)
-                    .join_round()
-                    .points(line.clone());
+                    .stroke_weight(model.settings.isobar_stroke_weight);
             }
-        });
 
-        // Check that the text is within the windows bounds
-        let win_rect = app.main_window().rect().pad(96.0);
-        let in_bounds = win_rect.contains(text_pos);
+            let font_size = model.settings.label_font_size * 2 / 3;
+            for (word, pos) in trough.keywords.iter().zip(trough.keyword_positions()) {
+                // Just above the line
+                let pos = vec2(pos.x, pos.y + font_size as f32);
+                draw.text(word)
+                    .font(model.display_font.clone())
+                    .font_size(font_size)
+                    .x_y(pos.x - 1.0, pos.y - 1.0)
+                    .color(WHITE);
+                draw.text(word)
+                    .font(model.display_font.clone())
+                    .font_size(font_size)
+                    .x_y(pos.x, pos.y)
+                    .color(BLACK);
+            }
+        }
 
-        if group.convex_hulls.len() > 0 && biggest_area > 500.0 && in_bounds && biggest_width > 64.0
-        {
-            let cur_value = group.display_values[model.day];
+        if let Some(text_pos) = geometry.label_pos {
+            let cur_value = group.display_values[model.bucket];
             let fmt = format!(
                 r#"
             {}
@@ -647,14 +550,14 @@ fn view(app: &App, model: &Model, frame: Frame) {
 
             draw.text(label)
                 .font(model.display_font.clone())
-                .font_size(18)
+                .font_size(model.settings.label_font_size)
                 .line_spacing(2.0)
                 .x_y(text_pos.x - 2.0, text_pos.y - 2.0)
                 .center_justify()
                 .color(WHITE);
             draw.text(label)
                 .font(model.display_font.clone())
-                .font_size(18)
+                .font_size(model.settings.label_font_size)
                 .line_spacing(2.0)
                 .x_y(text_pos.x, text_pos.y)
                 .center_justify()
@@ -662,28 +565,84 @@ fn view(app: &App, model: &Model, frame: Frame) {
         }
     }
 
+    if capture_frame.is_none() || model.settings.debug_in_capture {
+        let stats = Stats {
+            frame: model.frame,
+            bucket: model.bucket,
+            bucket_count: model.bucket_count(),
+            bucket_label: bucket_label(&model.settings, model.bucket),
+            step_millis: model.step_millis,
+            fps: app.fps(),
+            energy: model.stability.kinetic_energy,
+            hull_change: model.stability.hull_change,
+        };
+        model.overlays.draw(
+            &draw,
+            &model.node_groups,
+            &model.settings,
+            &stats,
+            &model.display_font,
+            app.main_window().rect(),
+        );
+    }
+
+    // Show the playback state while previewing
+    if capture_frame.is_none() && (model.paused || !model.capture) {
+        let status = format!(
+            "{}{} {}X{}",
+            if model.paused { "PAUSED  " } else { "" },
+            bucket_label(&model.settings, model.bucket).to_uppercase(),
+            model.speed,
+            if model.capture { "" } else { "  NOT CAPTURING" }
+        );
+        let win_rect = app.main_window().rect().pad(16.0);
+        draw.text(&status)
+            .font(model.display_font.clone())
+            .font_size(14)
+            .w(win_rect.w())
+            .x_y(win_rect.x(), win_rect.top())
+            .left_justify()
+            .color(BLACK);
+    }
+
     // Write the result of our drawing to the window's frame.
     draw.to_frame(app, &frame).unwrap();
 
-    let stab_frames = model.settings.stabilize_time * model.settings.frame_rate;
-    let end_frame = stab_frames + (model.settings.frame_rate * model.settings.day_seconds * 7);
+    let end_frame = model.capture_range().map(|(_, end_frame)| end_frame);
 
-    if model.frame > stab_frames && model.frame < end_frame {
-        let adjusted_frame = model.frame - stab_frames;
+    // Keep the panel out of captured frames
+    if capture_frame.is_none() {
+        model.panel.draw(app, &frame);
+    }
+
+    if let Some(adjusted_frame) = capture_frame {
         app.main_window().capture_frame(
-            format!(
-                "./out/{}/{:0>6}.png",
-                &model.settings.start_date, adjusted_frame
-            )
-            .as_str(),
+            format!("{}/{:0>6}.png", model.settings.out_dir(), adjusted_frame).as_str(),
         );
     }
 
-    if model.frame > end_frame {
+    if model.capture && matches!(end_frame, Some(end) if model.frame >= end) {
         std::process::exit(0);
     }
 }
 
+// Topics with a location that can't be found fall back to the centre
+fn warn_unlocated(data: &NodeGroupData, settings: &SimSettings) {
+    for item in data.groups.iter() {
+        if item.location.is_some() && locate(item, settings).is_none() {
+            println!(
+                "Couldn't find the location of {}, leaving it unanchored",
+                item.key
+            );
+        }
+    }
+}
+
+fn window_bounds(app: &App) -> Bounds {
+    let rect = app.main_window().rect();
+    Bounds::new(rect.left(), rect.right(), rect.bottom(), rect.top())
+}
+
 fn get_group_colour(id: usize) -> Rgba {
     match id {
         0 => rgba(0.97254902, 0.149019608, 0.0, 1.0),
@@ -702,6 +661,57 @@ fn get_group_colour(id: usize) -> Rgba {
     }
 }
 
-fn key_pressed(_app: &App, _model: &mut Model, _key: Key) {}
+fn key_pressed(_app: &App, model: &mut Model, key: Key) {
+    match key {
+        Key::Space => model.paused = !model.paused,
+        Key::Period => model.step_once = true,
+        Key::Right => model.jump_to_bucket(model.bucket + 1),
+        Key::Left => model.jump_to_bucket(model.bucket.saturating_sub(1)),
+        Key::R => model.restart(),
+        Key::Up => model.speed = f32::min(model.speed * 2.0, 8.0),
+        Key::Down => model.speed = f32::max(model.speed / 2.0, 0.125),
+        Key::C => model.capture = !model.capture,
+        Key::Key1 => model.overlays.nodes = !model.overlays.nodes,
+        Key::Key2 => model.overlays.springs = !model.overlays.springs,
+        Key::Key3 => model.overlays.velocities = !model.overlays.velocities,
+        Key::Key4 => model.overlays.clusters = !model.overlays.clusters,
+        Key::Key5 => model.overlays.bounds = !model.overlays.bounds,
+        Key::Key6 => model.overlays.stats = !model.overlays.stats,
+        Key::T => {
+            model.settings.spring_topology = model.settings.spring_topology.next();
+            for group in model.node_groups.iter_mut() {
+                group.reconnect(&model.settings);
+            }
+        }
+        Key::Tab => model.panel.visible = !model.panel.visible,
+        Key::S => model.save_settings(),
+        _ => {}
+    }
+}
 
-fn mouse_released(_app: &App, _model: &mut Model, _button: MouseButton) {}
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    if button != MouseButton::Left || model.panel.has_mouse() {
+        return;
+    }
+
+    let mouse = app.mouse.position();
+    model.drag = pick_group(
+        &model.node_groups,
+        glam::vec2(mouse.x, mouse.y),
+        model.settings.node_radius,
+    )
+    .map(|group| Drag { group, last: mouse });
+}
+
+fn mouse_released(app: &App, model: &mut Model, button: MouseButton) {
+    match button {
+        MouseButton::Left => model.drag = None,
+        MouseButton::Right if !model.panel.has_mouse() => {
+            model.gust = Some(Gust {
+                centre: app.mouse.position(),
+                frames_left: GUST_FRAMES,
+            })
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,131 @@
+// Sidecar files describing where each bucket of the data starts in the
+// rendered video, for the chapter markers and captions in the web player.
+use chrono::NaiveDate;
+use nannou_fluid_sim::{bucket_label, NodeGroup, SimSettings};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// How many topics to list against each bucket
+const TOP_TOPICS: usize = 5;
+
+#[derive(Serialize)]
+struct TopicValue {
+    key: String,
+    value: usize,
+}
+
+#[derive(Serialize)]
+struct BucketMarker {
+    // Seconds into the final video, in the shape videojs-markers expects
+    time: f32,
+    text: String,
+    bucket: usize,
+    // The number of the first captured image for the bucket
+    frame: u32,
+    topics: Vec<TopicValue>,
+}
+
+#[derive(Serialize)]
+pub struct Markers {
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    markers: Vec<BucketMarker>,
+}
+
+impl Markers {
+    pub fn new(settings: &SimSettings) -> Self {
+        Markers {
+            start_date: settings.start_date,
+            end_date: settings.end_date,
+            markers: Vec::new(),
+        }
+    }
+
+    /// Record the start of `bucket`, `offset_frames` after capture began.
+    pub fn add(
+        &mut self,
+        bucket: usize,
+        offset_frames: u32,
+        settings: &SimSettings,
+        groups: &[NodeGroup],
+    ) {
+        let mut topics = groups
+            .iter()
+            .map(|g| TopicValue {
+                key: g.label.clone(),
+                value: g.display_values[bucket],
+            })
+            .filter(|t| t.value > 0)
+            .collect::<Vec<TopicValue>>();
+        topics.sort_by_key(|t| Reverse(t.value));
+        topics.truncate(TOP_TOPICS);
+
+        self.markers.push(BucketMarker {
+            time: video_time(offset_frames, settings),
+            text: bucket_label(settings, bucket),
+            bucket,
+            frame: offset_frames + 1,
+            topics,
+        });
+    }
+
+    /// Forget the markers from `bucket` on, for when playback jumps back.
+    pub fn truncate(&mut self, bucket: usize) {
+        self.markers.retain(|m| m.bucket < bucket);
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) {
+        let file = File::create(path).unwrap();
+        serde_json::to_writer(BufWriter::new(file), self).unwrap();
+    }
+
+    /// Write a WebVTT chapters track, one cue per bucket.
+    pub fn write_vtt<P: AsRef<Path>>(&self, path: P, end_time: f32) {
+        let mut vtt = String::from("WEBVTT\n");
+
+        for (i, marker) in self.markers.iter().enumerate() {
+            let cue_end = match self.markers.get(i + 1) {
+                Some(next) => next.time,
+                None => end_time,
+            };
+            let topics = marker
+                .topics
+                .iter()
+                .map(|t| format!("{} ({})", t.key, t.value))
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            vtt.push_str(&format!(
+                "\n{}\n{} --> {}\n{}\n{}\n",
+                marker.text,
+                vtt_timestamp(marker.time),
+                vtt_timestamp(cue_end),
+                marker.text,
+                topics
+            ));
+        }
+
+        fs::write(path, vtt).unwrap();
+    }
+}
+
+/// Seconds into the final video for a captured frame, once it has been run
+/// through the ffmpeg commands in the README.
+pub fn video_time(offset_frames: u32, settings: &SimSettings) -> f32 {
+    offset_frames as f32 / settings.video_frame_rate as f32 / settings.video_speed
+}
+
+fn vtt_timestamp(seconds: f32) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
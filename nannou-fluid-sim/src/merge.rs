@@ -0,0 +1,310 @@
+// Joining data.json files from several fetches into one longer timeline, and
+// cutting out part of one:
+//
+//     cargo run --release -- merge <file> [<file> ...] [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--out path]
+//
+// Topics are matched up by key and given zeros for the buckets a file didn't
+// cover. Where files overlap the later one on the command line wins, so a day
+// fetched while it was still going is replaced by a later fetch of it.
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use nannou_fluid_sim::{
+    bucket_time, midnight, read_node_data, Keyword, Location, NodeGroupData, NodeGroupDataItem,
+};
+use std::collections::BTreeMap;
+use std::fs;
+
+// Everything known about a topic across the files
+struct Topic {
+    key: String,
+    location: Option<Location>,
+    parent: Option<String>,
+    counts: BTreeMap<NaiveDateTime, usize>,
+    keywords: BTreeMap<NaiveDateTime, Vec<Keyword>>,
+}
+
+pub fn run(args: &[String], default_out: &str) -> Result<(), String> {
+    let mut inputs = Vec::new();
+    let mut from = None;
+    let mut to = None;
+    let mut out = default_out.to_string();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut date = || {
+            let text = args.next().ok_or_else(|| format!("{} needs a date", arg))?;
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .map_err(|_| format!("Couldn't read the date {}, use YYYY-MM-DD", text))
+        };
+        match arg.as_str() {
+            "--from" => from = Some(date()?),
+            "--to" => to = Some(date()?),
+            "--out" => out = args.next().cloned().ok_or("--out needs a path")?,
+            path if !path.starts_with("--") => inputs.push(path.to_string()),
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+    if inputs.is_empty() {
+        return Err("Give at least one data.json to merge or slice".to_string());
+    }
+
+    let files = inputs
+        .iter()
+        .map(|path| read_node_data(path).map_err(|e| format!("{}: {}", path, e)))
+        .collect::<Result<Vec<NodeGroupData>, String>>()?;
+
+    let data = merge(&inputs, &files, from, to)?;
+    let json = serde_json::to_string(&data).map_err(|e| e.to_string())?;
+    fs::write(&out, json).map_err(|e| format!("Couldn't write {}: {}", out, e))?;
+    println!(
+        "Wrote {} topics over {} {}s, {} to {}, to {}",
+        data.groups.len(),
+        data.groups.first().map_or(0, |g| g.counts.len()),
+        data.bucket.name().to_lowercase(),
+        data.start_date,
+        data.end_date,
+        out
+    );
+    Ok(())
+}
+
+fn merge(
+    paths: &[String],
+    files: &[NodeGroupData],
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<NodeGroupData, String> {
+    let bucket = files[0].bucket;
+    let mut topics: Vec<Topic> = Vec::new();
+    let mut starts = Vec::new();
+
+    for (path, file) in paths.iter().zip(files.iter()) {
+        if file.bucket != bucket {
+            return Err(format!(
+                "{} is in {:?} buckets but {} is in {:?}",
+                path, file.bucket, paths[0], bucket
+            ));
+        }
+        let start = file.start_date;
+        starts.push(start);
+
+        for item in file.groups.iter() {
+            let topic = match topics.iter().position(|t| t.key == item.key) {
+                Some(i) => &mut topics[i],
+                None => {
+                    topics.push(Topic {
+                        key: item.key.clone(),
+                        location: None,
+                        parent: None,
+                        counts: BTreeMap::new(),
+                        keywords: BTreeMap::new(),
+                    });
+                    topics.last_mut().unwrap()
+                }
+            };
+            if item.location.is_some() {
+                topic.location = item.location.clone();
+            }
+            if item.parent.is_some() {
+                topic.parent = item.parent.clone();
+            }
+            for (i, count) in item.counts.iter().enumerate() {
+                topic.counts.insert(bucket_time(bucket, start, i), *count);
+            }
+            for (i, keywords) in item.keywords.iter().enumerate() {
+                topic
+                    .keywords
+                    .insert(bucket_time(bucket, start, i), keywords.clone());
+            }
+        }
+    }
+
+    // Every bucket has to fall on the same grid, which weekly buckets from
+    // files starting on different weekdays wouldn't
+    let first = midnight(*starts.iter().min().unwrap());
+    for (path, start) in paths.iter().zip(starts.iter()) {
+        if (midnight(*start) - first).num_seconds() % bucket.seconds() != 0 {
+            return Err(format!(
+                "{}'s {:?} buckets don't line up with {}'s",
+                path, bucket, paths[0]
+            ));
+        }
+    }
+    let last = topics
+        .iter()
+        .filter_map(|t| t.counts.keys().next_back())
+        .max()
+        .cloned()
+        .unwrap_or(first);
+
+    // Slice out the buckets that start within the dates asked for
+    let step = Duration::seconds(bucket.seconds());
+    let mut times = Vec::new();
+    let mut time = first;
+    while time <= last {
+        let before_from = matches!(from, Some(from) if time.date() < from);
+        let after_to = matches!(to, Some(to) if time.date() > to);
+        if !before_from && !after_to {
+            times.push(time);
+        }
+        time += step;
+    }
+    let (start, end) = match (times.first(), times.last()) {
+        (Some(start), Some(end)) => (*start, *end),
+        _ => return Err("There's no data between those dates".to_string()),
+    };
+
+    let has_keywords = topics.iter().any(|t| !t.keywords.is_empty());
+    let groups = topics
+        .into_iter()
+        .map(|topic| {
+            let counts = times
+                .iter()
+                .map(|t| topic.counts.get(t).cloned().unwrap_or(0))
+                .collect::<Vec<usize>>();
+            let keywords = if has_keywords {
+                times
+                    .iter()
+                    .map(|t| topic.keywords.get(t).cloned().unwrap_or_default())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            (topic, counts, keywords)
+        })
+        // Topics with nothing in the slice would never appear
+        .filter(|(_, counts, _)| counts.iter().any(|c| *c > 0))
+        .enumerate()
+        .map(|(index, (topic, counts, keywords))| NodeGroupDataItem {
+            key: topic.key,
+            index,
+            counts,
+            location: topic.location,
+            keywords,
+            parent: topic.parent,
+        })
+        .collect();
+
+    Ok(NodeGroupData {
+        groups,
+        start_date: start.date(),
+        end_date: end.date(),
+        bucket,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{counts, date};
+    use nannou_fluid_sim::Bucket;
+
+    // Data starting on `start` with the given counts for each topic
+    fn file(bucket: Bucket, start: NaiveDate, topics: &[(&str, &[usize])]) -> NodeGroupData {
+        let buckets = topics.iter().map(|(_, c)| c.len()).max().unwrap_or(1);
+        NodeGroupData {
+            groups: topics
+                .iter()
+                .enumerate()
+                .map(|(index, (key, counts))| NodeGroupDataItem {
+                    key: key.to_string(),
+                    index,
+                    counts: counts.to_vec(),
+                    location: None,
+                    keywords: Vec::new(),
+                    parent: None,
+                })
+                .collect(),
+            start_date: start,
+            end_date: bucket_time(bucket, start, buckets - 1).date(),
+            bucket,
+        }
+    }
+
+    fn paths(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("{}.json", i)).collect()
+    }
+
+    #[test]
+    fn later_files_win_where_they_overlap() {
+        let files = [
+            file(Bucket::Day, date(2021, 3, 1), &[("floods", &[1, 2, 3])]),
+            file(
+                Bucket::Day,
+                date(2021, 3, 3),
+                &[("floods", &[30, 4]), ("bushfire", &[0, 5])],
+            ),
+        ];
+        let data = merge(&paths(2), &files, None, None).unwrap();
+
+        assert_eq!(data.start_date, date(2021, 3, 1));
+        assert_eq!(data.end_date, date(2021, 3, 4));
+        assert_eq!(counts(&data, "floods"), [1, 2, 30, 4]);
+        assert_eq!(counts(&data, "bushfire"), [0, 0, 0, 5]);
+        let indices = data.groups.iter().map(|g| g.index).collect::<Vec<usize>>();
+        assert_eq!(indices, [0, 1]);
+    }
+
+    #[test]
+    fn gaps_between_files_are_zeros() {
+        let files = [
+            file(Bucket::Day, date(2021, 3, 1), &[("floods", &[1])]),
+            file(Bucket::Day, date(2021, 3, 4), &[("floods", &[4])]),
+        ];
+        let data = merge(&paths(2), &files, None, None).unwrap();
+        assert_eq!(counts(&data, "floods"), [1, 0, 0, 4]);
+    }
+
+    #[test]
+    fn slices_between_dates() {
+        let files = [file(
+            Bucket::Day,
+            date(2021, 3, 1),
+            &[("floods", &[1, 2, 3, 4]), ("mardigras", &[7, 0, 0, 0])],
+        )];
+        let data = merge(
+            &paths(1),
+            &files,
+            Some(date(2021, 3, 2)),
+            Some(date(2021, 3, 3)),
+        )
+        .unwrap();
+
+        assert_eq!(data.start_date, date(2021, 3, 2));
+        assert_eq!(data.end_date, date(2021, 3, 3));
+        assert_eq!(counts(&data, "floods"), [2, 3]);
+        // Nothing in the slice, so it's left out
+        assert_eq!(data.groups.len(), 1);
+        assert_eq!(data.groups[0].index, 0);
+    }
+
+    #[test]
+    fn an_empty_slice_is_an_error() {
+        let files = [file(Bucket::Day, date(2021, 3, 1), &[("floods", &[1, 2])])];
+        let result = merge(&paths(1), &files, Some(date(2021, 4, 1)), None);
+        assert_eq!(result.err().unwrap(), "There's no data between those dates");
+    }
+
+    #[test]
+    fn files_have_to_share_a_bucket_grid() {
+        let files = [
+            file(Bucket::Day, date(2021, 3, 1), &[("floods", &[1])]),
+            file(Bucket::Week, date(2021, 3, 8), &[("floods", &[1])]),
+        ];
+        let result = merge(&paths(2), &files, None, None);
+        assert_eq!(
+            result.err().unwrap(),
+            "1.json is in Week buckets but 0.json is in Day"
+        );
+
+        // Weeks starting on a Monday and on a Wednesday
+        let files = [
+            file(Bucket::Week, date(2021, 3, 1), &[("floods", &[1])]),
+            file(Bucket::Week, date(2021, 3, 10), &[("floods", &[1])]),
+        ];
+        let result = merge(&paths(2), &files, None, None);
+        assert_eq!(
+            result.err().unwrap(),
+            "1.json's Week buckets don't line up with 0.json's"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+// Debug overlays for seeing what the simulation is doing underneath the hulls.
+use nannou::prelude::*;
+use nannou_fluid_sim::{Boundary, NodeClass, NodeGroup, SimSettings};
+
+pub struct Overlays {
+    pub nodes: bool,
+    pub springs: bool,
+    pub velocities: bool,
+    pub clusters: bool,
+    pub bounds: bool,
+    pub stats: bool,
+}
+
+/// What the stats overlay reports.
+pub struct Stats {
+    pub frame: u32,
+    pub bucket: usize,
+    pub bucket_count: usize,
+    pub bucket_label: String,
+    pub step_millis: f32,
+    pub fps: f32,
+    pub energy: f32,
+    pub hull_change: f32,
+}
+
+impl Overlays {
+    pub fn new() -> Self {
+        Overlays {
+            nodes: false,
+            springs: false,
+            velocities: false,
+            clusters: false,
+            bounds: false,
+            stats: false,
+        }
+    }
+
+    pub fn draw(
+        &self,
+        draw: &Draw,
+        groups: &[NodeGroup],
+        settings: &SimSettings,
+        stats: &Stats,
+        font: &nannou::text::Font,
+        win_rect: Rect,
+    ) {
+        for group in groups {
+            let colour = debug_colour(group.id, groups.len());
+            let nodes = group.nodes();
+
+            if self.springs {
+                for spring in group.springs() {
+                    if let (Some(from), Some(to)) = (nodes.get(spring.from), nodes.get(spring.to)) {
+                        draw.line()
+                            .start(pt2(from.x, from.y))
+                            .end(pt2(to.x, to.y))
+                            .weight(1.0)
+                            .color(rgba(0.0, 0.0, 0.0, 0.25));
+                    }
+                }
+            }
+
+            for (i, node) in nodes.iter().enumerate() {
+                let pos = pt2(node.x, node.y);
+
+                if self.nodes {
+                    draw.ellipse().xy(pos).radius(2.0).color(colour);
+                }
+
+                if self.velocities {
+                    // Draw where the node would be in half a second
+                    let end = pos + vec2(node.velocity.x, node.velocity.y) * 0.5;
+                    draw.line().start(pos).end(end).weight(1.0).color(colour);
+                }
+
+                if self.clusters {
+                    match group.node_classes.get(i) {
+                        Some(NodeClass::Core) => {
+                            draw.ellipse().xy(pos).radius(3.0).color(WHITE);
+                        }
+                        Some(NodeClass::Edge) => {
+                            draw.ellipse().xy(pos).radius(3.0).color(YELLOW);
+                        }
+                        Some(NodeClass::Noise) => {
+                            draw.ellipse().xy(pos).radius(3.0).color(BLACK);
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        if self.bounds {
+            match (&settings.boundary, settings.boundary.polygon()) {
+                (_, Some(points)) => {
+                    let mut outline = points.iter().map(|p| pt2(p[0], p[1])).collect::<Vec<_>>();
+                    if let Some(first) = outline.first().cloned() {
+                        outline.push(first);
+                    }
+                    draw.polyline().weight(1.0).points(outline).color(RED);
+                }
+                (Boundary::Circle { x, y, radius }, None) => {
+                    draw.ellipse()
+                        .x_y(*x, *y)
+                        .radius(*radius)
+                        .no_fill()
+                        .stroke(RED)
+                        .stroke_weight(1.0);
+                }
+                _ => {
+                    let r = settings.repel_rect;
+                    draw.rect()
+                        .x_y((r.left + r.right) / 2.0, (r.bottom + r.top) / 2.0)
+                        .w_h(r.right - r.left, r.top - r.bottom)
+                        .no_fill()
+                        .stroke(RED)
+                        .stroke_weight(1.0);
+                }
+            }
+        }
+
+        if self.stats {
+            let node_count: usize = groups.iter().map(|g| g.nodes().len()).sum();
+            let text = format!(
+                "FRAME {}  {} ({}/{})  NODES {}\nSTEP {:.1}MS  FPS {:.0}\nENERGY {:.2}  HULL CHANGE {:.4}",
+                stats.frame,
+                stats.bucket_label.to_uppercase(),
+                stats.bucket + 1,
+                stats.bucket_count,
+                node_count,
+                stats.step_millis,
+                stats.fps,
+                stats.energy,
+                stats.hull_change
+            );
+            let rect = win_rect.pad(16.0);
+            draw.text(&text)
+                .font(font.clone())
+                .font_size(14)
+                .w(rect.w())
+                .x_y(rect.x(), rect.bottom() + 16.0)
+                .left_justify()
+                .color(BLACK);
+        }
+    }
+}
+
+/// A distinct colour per group, unlike the alternating isobar colours.
+fn debug_colour(id: usize, group_count: usize) -> Hsv {
+    let hue = id as f32 / std::cmp::max(group_count, 1) as f32;
+    hsv(hue, 0.8, 0.9)
+}
@@ -0,0 +1,402 @@
+// In-window sliders for tuning the sim settings while it runs.
+use nannou::prelude::*;
+use nannou::ui::prelude::*;
+use nannou_fluid_sim::SimSettings;
+
+widget_ids! {
+    struct Ids {
+        title,
+        node_radius,
+        node_max_velocity,
+        node_attract_ramp,
+        node_attract_strength,
+        node_attract_strength_friendly,
+        affinity_strength,
+        parent_attract_strength,
+        node_damping,
+        physics_rate,
+        attractor,
+        attractor_strength,
+        shape_title,
+        spring_length,
+        spring_stiffness,
+        spring_damping,
+        spring_rebuild_seconds,
+        buddy_count,
+        nearest_count,
+        hull_cluster_distance,
+        hull_cluster_min_points,
+        hull_concavity,
+        hull_simplify,
+        isobar_stroke_weight,
+        label_font_size,
+        trough_keywords,
+        trough_dash,
+        save,
+    }
+}
+
+pub struct Panel {
+    ui: Ui,
+    ids: Ids,
+    pub visible: bool,
+    // The attractor whose strength the panel is showing
+    attractor: usize,
+}
+
+/// What happened in the panel this frame.
+pub struct PanelEvents {
+    pub changed: bool,
+    pub save: bool,
+}
+
+impl Panel {
+    pub fn new(app: &App, window: WindowId) -> Self {
+        let mut ui = app.new_ui().window(window).build().unwrap();
+        let ids = Ids::new(ui.widget_id_generator());
+        Panel {
+            ui,
+            ids,
+            visible: false,
+            attractor: 0,
+        }
+    }
+
+    pub fn update(&mut self, settings: &mut SimSettings) -> PanelEvents {
+        let mut events = PanelEvents {
+            changed: false,
+            save: false,
+        };
+        if !self.visible {
+            return events;
+        }
+
+        let ui = &mut self.ui.set_widgets();
+        let ids = &self.ids;
+
+        widget::Text::new("SETTINGS")
+            .top_left_with_margin(16.0)
+            .font_size(14)
+            .rgb(0.0, 0.0, 0.0)
+            .set(ids.title, ui);
+
+        let mut changed = false;
+
+        // Physics
+        changed |= slider(
+            ui,
+            ids.node_radius,
+            "Node radius",
+            &mut settings.node_radius,
+            10.0,
+            400.0,
+        );
+        changed |= slider(
+            ui,
+            ids.node_max_velocity,
+            "Max velocity",
+            &mut settings.node_max_velocity,
+            2.0,
+            200.0,
+        );
+        changed |= slider(
+            ui,
+            ids.node_attract_ramp,
+            "Attract ramp",
+            &mut settings.node_attract_ramp,
+            0.1,
+            5.0,
+        );
+        changed |= slider(
+            ui,
+            ids.node_attract_strength,
+            "Attract strength",
+            &mut settings.node_attract_strength,
+            -5.0,
+            5.0,
+        );
+        changed |= slider(
+            ui,
+            ids.node_attract_strength_friendly,
+            "Friendly strength",
+            &mut settings.node_attract_strength_friendly,
+            -5.0,
+            5.0,
+        );
+        changed |= slider(
+            ui,
+            ids.affinity_strength,
+            "Affinity strength",
+            &mut settings.affinity_strength,
+            0.0,
+            5.0,
+        );
+        changed |= slider(
+            ui,
+            ids.parent_attract_strength,
+            "Parent pull",
+            &mut settings.parent_attract_strength,
+            -10.0,
+            0.0,
+        );
+        changed |= slider(
+            ui,
+            ids.node_damping,
+            "Damping /s",
+            &mut settings.node_damping,
+            0.0,
+            60.0,
+        );
+        let mut physics_rate = settings.physics_rate as f32;
+        if slider(
+            ui,
+            ids.physics_rate,
+            "Physics steps /s",
+            &mut physics_rate,
+            20.0,
+            240.0,
+        ) {
+            settings.physics_rate = physics_rate.round() as u32;
+            changed = true;
+        }
+
+        // Attractors, one at a time
+        if !settings.attractors.is_empty() {
+            self.attractor = self.attractor.min(settings.attractors.len() - 1);
+            for _click in widget::Button::new()
+                .label(&format!(
+                    "Attractor {} of {}",
+                    self.attractor + 1,
+                    settings.attractors.len()
+                ))
+                .label_font_size(12)
+                .w_h(240.0, 24.0)
+                .down(12.0)
+                .set(ids.attractor, ui)
+            {
+                self.attractor = (self.attractor + 1) % settings.attractors.len();
+            }
+            changed |= slider(
+                ui,
+                ids.attractor_strength,
+                "Attractor strength",
+                &mut settings.attractors[self.attractor].strength,
+                -10.0,
+                10.0,
+            );
+        }
+
+        widget::Text::new("SPRINGS AND HULLS")
+            .top_left_with_margins(16.0, 272.0)
+            .font_size(14)
+            .rgb(0.0, 0.0, 0.0)
+            .set(ids.shape_title, ui);
+
+        // Springs, for whichever topology is in use
+        let topology = format!("{:?}", settings.spring_topology);
+        let (spring_length, spring_stiffness) = settings.topology_spring_mut();
+        changed |= slider(
+            ui,
+            ids.spring_length,
+            &format!("{} length", topology),
+            spring_length,
+            0.0,
+            400.0,
+        );
+        changed |= slider(
+            ui,
+            ids.spring_stiffness,
+            &format!("{} stiffness", topology),
+            spring_stiffness,
+            0.0,
+            10.0,
+        );
+        changed |= slider(
+            ui,
+            ids.spring_damping,
+            "Spring damping",
+            &mut settings.spring_damping,
+            0.0,
+            1.0,
+        );
+        changed |= slider(
+            ui,
+            ids.spring_rebuild_seconds,
+            "Rebuild seconds",
+            &mut settings.spring_rebuild_seconds,
+            0.0,
+            10.0,
+        );
+        changed |= count_slider(
+            ui,
+            ids.buddy_count,
+            "Buddy count",
+            &mut settings.buddy_count,
+            1,
+            12,
+        );
+        changed |= count_slider(
+            ui,
+            ids.nearest_count,
+            "Nearest count",
+            &mut settings.nearest_count,
+            1,
+            12,
+        );
+
+        // Hulls
+        changed |= slider(
+            ui,
+            ids.hull_cluster_distance,
+            "Cluster distance",
+            &mut settings.hull_cluster_distance,
+            5.0,
+            120.0,
+        );
+        changed |= count_slider(
+            ui,
+            ids.hull_cluster_min_points,
+            "Cluster min points",
+            &mut settings.hull_cluster_min_points,
+            1,
+            60,
+        );
+        changed |= slider(
+            ui,
+            ids.hull_concavity,
+            "Concavity",
+            &mut settings.hull_concavity,
+            0.5,
+            10.0,
+        );
+        changed |= slider(
+            ui,
+            ids.hull_simplify,
+            "Simplify",
+            &mut settings.hull_simplify,
+            0.0,
+            10.0,
+        );
+
+        // Rendering
+        changed |= slider(
+            ui,
+            ids.isobar_stroke_weight,
+            "Stroke weight",
+            &mut settings.isobar_stroke_weight,
+            0.5,
+            12.0,
+        );
+        let mut font_size = settings.label_font_size as f32;
+        if slider(
+            ui,
+            ids.label_font_size,
+            "Label size",
+            &mut font_size,
+            8.0,
+            48.0,
+        ) {
+            settings.label_font_size = font_size.round() as u32;
+            changed = true;
+        }
+        changed |= count_slider(
+            ui,
+            ids.trough_keywords,
+            "Trough keywords",
+            &mut settings.trough_keywords,
+            0,
+            8,
+        );
+        changed |= slider(
+            ui,
+            ids.trough_dash,
+            "Trough dash",
+            &mut settings.trough_dash,
+            0.0,
+            40.0,
+        );
+
+        for _click in widget::Button::new()
+            .label("Save config")
+            .label_font_size(12)
+            .w_h(240.0, 24.0)
+            .down(12.0)
+            .set(ids.save, ui)
+        {
+            events.save = true;
+        }
+
+        events.changed = changed;
+        events
+    }
+
+    /// Whether the mouse is on one of the panel's widgets, so a click is meant
+    /// for the panel rather than the systems underneath it.
+    pub fn has_mouse(&self) -> bool {
+        let input = &self.ui.global_input().current;
+        self.visible
+            && (input.widget_capturing_mouse.is_some()
+                || matches!(input.widget_under_mouse, Some(id) if id != self.ui.window))
+    }
+
+    pub fn draw(&self, app: &App, frame: &Frame) {
+        if self.visible {
+            self.ui.draw_to_frame(app, frame).unwrap();
+        }
+    }
+}
+
+fn slider(
+    ui: &mut UiCell,
+    id: widget::Id,
+    label: &str,
+    value: &mut f32,
+    min: f32,
+    max: f32,
+) -> bool {
+    let text = format!("{} {:.2}", label, value);
+    match widget::Slider::new(*value, min, max)
+        .w_h(240.0, 24.0)
+        .down(4.0)
+        .label(&text)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .set(id, ui)
+    {
+        Some(new_value) => {
+            *value = new_value;
+            true
+        }
+        None => false,
+    }
+}
+
+// A slider for a whole number setting.
+fn count_slider(
+    ui: &mut UiCell,
+    id: widget::Id,
+    label: &str,
+    value: &mut usize,
+    min: usize,
+    max: usize,
+) -> bool {
+    let text = format!("{} {}", label, value);
+    match widget::Slider::new(*value as f32, min as f32, max as f32)
+        .w_h(240.0, 24.0)
+        .down(4.0)
+        .label(&text)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .set(id, ui)
+    {
+        Some(new_value) if new_value.round() as usize != *value => {
+            *value = new_value.round() as usize;
+            true
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,131 @@
+// Placing topics on the map, from a lat/lon or the name of a region.
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+// Rough centres of places a topic might be about, as (name, lat, lon)
+const REGIONS: [(&str, f32, f32); 32] = [
+    ("sydney", -33.87, 151.21),
+    ("newcastle", -32.93, 151.78),
+    ("wollongong", -34.42, 150.89),
+    ("central coast", -33.43, 151.34),
+    ("hunter", -32.55, 151.17),
+    ("illawarra", -34.50, 150.80),
+    ("blue mountains", -33.71, 150.31),
+    ("southern highlands", -34.48, 150.42),
+    ("canberra", -35.28, 149.13),
+    ("south coast", -35.90, 150.08),
+    ("snowy mountains", -36.43, 148.39),
+    ("riverina", -34.75, 146.55),
+    ("wagga wagga", -35.12, 147.37),
+    ("albury", -36.08, 146.92),
+    ("murray", -35.50, 144.50),
+    ("central west", -33.28, 148.50),
+    ("orange", -33.28, 149.10),
+    ("bathurst", -33.42, 149.58),
+    ("dubbo", -32.25, 148.60),
+    ("orana", -31.50, 148.00),
+    ("far west", -31.50, 143.50),
+    ("broken hill", -31.95, 141.45),
+    ("new england", -30.50, 151.50),
+    ("tamworth", -31.09, 150.93),
+    ("armidale", -30.51, 151.67),
+    ("mid north coast", -31.40, 152.60),
+    ("port macquarie", -31.43, 152.91),
+    ("coffs harbour", -30.30, 153.11),
+    ("northern rivers", -28.80, 153.30),
+    ("lismore", -28.81, 153.28),
+    ("byron bay", -28.64, 153.61),
+    ("north west", -30.00, 149.00),
+];
+
+/// Where a topic belongs on the map.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Location {
+    LatLon { lat: f32, lon: f32 },
+    // One of the names in `REGIONS`, in any case
+    Region(String),
+}
+
+impl Location {
+    /// The lat/lon of the location, if it's a region we know about.
+    pub fn lat_lon(&self) -> Option<(f32, f32)> {
+        match self {
+            Location::LatLon { lat, lon } => Some((*lat, *lon)),
+            Location::Region(name) => {
+                let name = name.trim().to_lowercase();
+                REGIONS
+                    .iter()
+                    .find(|region| region.0 == name)
+                    .map(|region| (region.1, region.2))
+            }
+        }
+    }
+}
+
+/// An equirectangular projection from degrees onto the map image, which is
+/// drawn centred in the window at its own size.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MapProjection {
+    // The longitudes of the left and right edges of the image
+    pub west: f32,
+    pub east: f32,
+    // The latitudes of the top and bottom edges of the image
+    pub north: f32,
+    pub south: f32,
+    // The size of the image in pixels
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for MapProjection {
+    // Fitted to assets/map.png using the 141°E and 29°S borders, Cape Byron and Cape Howe
+    fn default() -> Self {
+        MapProjection {
+            west: 139.63,
+            east: 154.03,
+            north: -26.84,
+            south: -37.67,
+            width: 894.0,
+            height: 812.0,
+        }
+    }
+}
+
+impl MapProjection {
+    /// The window position of a lat/lon.
+    pub fn project(&self, lat: f32, lon: f32) -> Vec2 {
+        let x = (lon - self.west) / (self.east - self.west);
+        let y = (lat - self.south) / (self.north - self.south);
+        vec2((x - 0.5) * self.width, (y - 0.5) * self.height)
+    }
+
+    /// The lat/lon of a window position, the inverse of `project`.
+    pub fn unproject(&self, point: Vec2) -> (f32, f32) {
+        let x = point.x / self.width + 0.5;
+        let y = point.y / self.height + 0.5;
+        let lon = self.west + x * (self.east - self.west);
+        let lat = self.south + y * (self.north - self.south);
+        (lat, lon)
+    }
+
+    /// The window position of a location, if it can be found.
+    pub fn locate(&self, location: &Location) -> Option<Vec2> {
+        location.lat_lon().map(|(lat, lon)| self.project(lat, lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unproject_undoes_project() {
+        let projection = MapProjection::default();
+        for &(lat, lon) in [(-33.87, 151.21), (-31.95, 141.45), (-28.64, 153.61)].iter() {
+            let (back_lat, back_lon) = projection.unproject(projection.project(lat, lon));
+            assert!((back_lat - lat).abs() < 1e-4, "{} became {}", lat, back_lat);
+            assert!((back_lon - lon).abs() < 1e-4, "{} became {}", lon, back_lon);
+        }
+    }
+}
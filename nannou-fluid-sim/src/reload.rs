@@ -0,0 +1,81 @@
+// Watches the data and config files so they can be reloaded without
+// restarting the app.
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+pub struct Reloader {
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+    data_file: Option<OsString>,
+    config_file: Option<OsString>,
+}
+
+/// Which of the watched files changed since the last poll.
+pub struct Changes {
+    pub data: bool,
+    pub config: bool,
+}
+
+impl Reloader {
+    pub fn new(data_path: &str, config_path: &str) -> Self {
+        let (tx, events) = channel();
+        let mut watcher = watcher(tx, Duration::from_millis(250)).unwrap();
+
+        // Watch the directories rather than the files, editors often replace a
+        // file rather than write to it and the config may not exist yet.
+        let data_dir = parent_dir(data_path);
+        let config_dir = parent_dir(config_path);
+        watcher
+            .watch(data_dir, RecursiveMode::NonRecursive)
+            .unwrap();
+        if config_dir != data_dir {
+            watcher
+                .watch(config_dir, RecursiveMode::NonRecursive)
+                .unwrap();
+        }
+
+        Reloader {
+            _watcher: watcher,
+            events,
+            data_file: Path::new(data_path).file_name().map(|f| f.to_owned()),
+            config_file: Path::new(config_path).file_name().map(|f| f.to_owned()),
+        }
+    }
+
+    pub fn poll(&self) -> Changes {
+        let mut changes = Changes {
+            data: false,
+            config: false,
+        };
+
+        while let Ok(event) = self.events.try_recv() {
+            let path = match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => path,
+                _ => continue,
+            };
+            let file_name = path.file_name().map(|f| f.to_owned());
+
+            if file_name.is_some() && file_name == self.data_file {
+                changes.data = true;
+            }
+            if file_name.is_some() && file_name == self.config_file {
+                changes.config = true;
+            }
+        }
+
+        changes
+    }
+}
+
+fn parent_dir(path: &str) -> &Path {
+    match Path::new(path).parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    }
+}
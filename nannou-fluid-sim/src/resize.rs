@@ -0,0 +1,147 @@
+// Which nodes go, and where new ones appear, when a group changes size.
+use crate::{random_range, Node, NodeGroup};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Removal {
+    // Any node
+    Random,
+    // The nodes furthest from the centre of the group first
+    Periphery,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Spawn {
+    // All stacked on one random existing node, give or take a pixel
+    Pile,
+    // Spread along the outside of the group's hulls
+    HullBoundary,
+    // Around the node with the most neighbours
+    DensityPeak,
+}
+
+// Piled nodes are spread this far so springs and forces can push them apart
+const PILE_RADIUS: f32 = 1.0;
+
+/// The indices of `count` nodes to remove, never including `keep`.
+pub fn removal_indices(
+    nodes: &[Node],
+    count: usize,
+    removal: Removal,
+    keep: Option<usize>,
+) -> Vec<usize> {
+    let mut candidates = (0..nodes.len())
+        .filter(|i| Some(*i) != keep)
+        .collect::<Vec<usize>>();
+
+    match removal {
+        Removal::Random => {
+            let mut indices = Vec::new();
+            while indices.len() < count && !candidates.is_empty() {
+                let pick = random_range(0, candidates.len());
+                indices.push(candidates.swap_remove(pick));
+            }
+            indices
+        }
+        Removal::Periphery => {
+            let (cx, cy) = centre(nodes);
+            let distance = |i: &usize| (nodes[*i].x - cx).powi(2) + (nodes[*i].y - cy).powi(2);
+            candidates.sort_by(|a, b| {
+                distance(b)
+                    .partial_cmp(&distance(a))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            candidates.truncate(count);
+            candidates
+        }
+    }
+}
+
+/// Positions for `count` new nodes.
+pub fn spawn_points(
+    group: &NodeGroup,
+    count: usize,
+    spawn: Spawn,
+    neighbour_distance: f32,
+) -> Vec<(f32, f32)> {
+    let nodes = group.nodes();
+
+    // Nothing to grow from, start somewhere random
+    if nodes.is_empty() {
+        let x = random_range(-200.0, 200.0);
+        let y = random_range(-200.0, 200.0);
+        return (0..count).map(|_| jitter(x, y, PILE_RADIUS)).collect();
+    }
+
+    match spawn {
+        Spawn::HullBoundary if !group.convex_hulls.is_empty() => {
+            (0..count).map(|_| point_on_hulls(group)).collect()
+        }
+        Spawn::DensityPeak => {
+            let (x, y) = density_peak(nodes, neighbour_distance);
+            (0..count)
+                .map(|_| jitter(x, y, neighbour_distance / 2.0))
+                .collect()
+        }
+        // Fall back to piling up until the group is big enough to have hulls
+        _ => {
+            let target = &nodes[random_range(0, nodes.len())];
+            (0..count)
+                .map(|_| jitter(target.x, target.y, PILE_RADIUS))
+                .collect()
+        }
+    }
+}
+
+fn centre(nodes: &[Node]) -> (f32, f32) {
+    let count = nodes.len().max(1) as f32;
+    let x = nodes.iter().map(|n| n.x).sum::<f32>() / count;
+    let y = nodes.iter().map(|n| n.y).sum::<f32>() / count;
+    (x, y)
+}
+
+// A random point along the hulls, weighted by their length
+fn point_on_hulls(group: &NodeGroup) -> (f32, f32) {
+    let segments = group
+        .convex_hulls
+        .iter()
+        .flat_map(|hull| hull.0.windows(2))
+        .map(|w| {
+            let length = ((w[1].x - w[0].x).powi(2) + (w[1].y - w[0].y).powi(2)).sqrt();
+            (w[0], w[1], length)
+        })
+        .collect::<Vec<_>>();
+    let total: f32 = segments.iter().map(|s| s.2).sum();
+
+    let mut along = random_range(0.0, total);
+    for (a, b, length) in segments.iter() {
+        if along <= *length && *length > 0.0 {
+            let t = along / length;
+            return (a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+        }
+        along -= length;
+    }
+
+    let first = group.convex_hulls[0].0[0];
+    (first.x, first.y)
+}
+
+fn density_peak(nodes: &[Node], neighbour_distance: f32) -> (f32, f32) {
+    let max_sq = neighbour_distance * neighbour_distance;
+    let neighbours = |a: &Node| {
+        nodes
+            .iter()
+            .filter(|b| (a.x - b.x).powi(2) + (a.y - b.y).powi(2) < max_sq)
+            .count()
+    };
+
+    let peak = nodes.iter().max_by_key(|n| neighbours(n)).unwrap();
+    (peak.x, peak.y)
+}
+
+fn jitter(x: f32, y: f32, radius: f32) -> (f32, f32) {
+    let angle = random_range(0.0, 2.0 * PI);
+    let d = random_range(0.0, radius);
+    (x + angle.cos() * d, y + angle.sin() * d)
+}
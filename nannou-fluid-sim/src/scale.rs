@@ -0,0 +1,156 @@
+// Turning the post counts in the data into the number of nodes in each group.
+use crate::{NodeGroupData, SimSettings};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Curve {
+    // Nodes in proportion to the count
+    Linear,
+    // Flattens out the biggest counts the most
+    Log,
+    Sqrt,
+    // What data-gen used to do, pulling small counts up towards the big ones
+    CubicEase,
+    // By where the count ranks among every count in the data
+    Quantile,
+}
+
+/// The node counts for each bucket of each group in the data. Every curve gives
+/// the biggest count `max count / count_divider` nodes, then the result is
+/// kept between `min_nodes` and `max_nodes`. Buckets without posts have no
+/// nodes.
+pub fn node_counts(data: &NodeGroupData, settings: &SimSettings) -> Vec<Vec<usize>> {
+    let mut all_counts = data
+        .groups
+        .iter()
+        .flat_map(|g| g.counts.iter().cloned())
+        .filter(|c| *c > 0)
+        .collect::<Vec<usize>>();
+    all_counts.sort_unstable();
+    let max_count = all_counts.last().cloned().unwrap_or(0) as f32;
+
+    let scale = |count: usize| {
+        if count == 0 || max_count == 0.0 {
+            return 0;
+        }
+        let v = count as f32;
+        let fraction = match settings.count_curve {
+            Curve::Linear => v / max_count,
+            Curve::Log => (1.0 + v).ln() / (1.0 + max_count).ln(),
+            Curve::Sqrt => (v / max_count).sqrt(),
+            Curve::CubicEase => 1.0 - (1.0 - v / max_count).powi(3),
+            Curve::Quantile => {
+                let rank = all_counts.partition_point(|c| *c <= count);
+                rank as f32 / all_counts.len() as f32
+            }
+        };
+        let nodes = (fraction * max_count / settings.count_divider.max(0.001)).round() as usize;
+        match settings.max_nodes {
+            Some(max_nodes) => nodes.max(settings.min_nodes).min(max_nodes),
+            None => nodes.max(settings.min_nodes),
+        }
+    };
+
+    data.groups
+        .iter()
+        .map(|g| g.counts.iter().map(|c| scale(*c)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NodeGroupDataItem;
+    use chrono::NaiveDate;
+
+    // Data with a topic for each list of counts
+    fn data(topics: &[&[usize]]) -> NodeGroupData {
+        NodeGroupData {
+            groups: topics
+                .iter()
+                .enumerate()
+                .map(|(index, counts)| NodeGroupDataItem {
+                    key: format!("topic {}", index),
+                    index,
+                    counts: counts.to_vec(),
+                    location: None,
+                    keywords: Vec::new(),
+                    parent: None,
+                })
+                .collect(),
+            start_date: NaiveDate::from_ymd_opt(2021, 3, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2021, 3, 8).unwrap(),
+            bucket: Default::default(),
+        }
+    }
+
+    // data-gen's scaleFunc, with its DIVIDER of 50 and CURVE_DEGREE of 3
+    fn scale_func(max_value: f64, v: f64) -> usize {
+        let f = 1.0 - v / max_value;
+        let curved = f.powi(3);
+        let inv = 1.0 - curved;
+        (inv * max_value / 50.0).round() as usize
+    }
+
+    #[test]
+    fn cubic_ease_matches_data_gen() {
+        let topics: [&[usize]; 2] = [&[0, 1, 10, 120, 999, 2500, 4999, 5000], &[37, 420, 4200]];
+        let settings = SimSettings {
+            count_curve: Curve::CubicEase,
+            count_divider: 50.0,
+            ..SimSettings::default()
+        };
+
+        let nodes = node_counts(&data(&topics), &settings);
+        for (counts, nodes) in topics.iter().zip(nodes.iter()) {
+            let expected = counts
+                .iter()
+                .map(|c| scale_func(5000.0, *c as f64))
+                .collect::<Vec<usize>>();
+            assert_eq!(*nodes, expected);
+        }
+    }
+
+    #[test]
+    fn node_counts_are_clamped() {
+        let topics: [&[usize]; 1] = [&[0, 10, 500, 5000]];
+        let settings = SimSettings {
+            count_curve: Curve::Linear,
+            count_divider: 10.0,
+            min_nodes: 5,
+            max_nodes: Some(100),
+            ..SimSettings::default()
+        };
+        // Empty buckets stay empty rather than going up to `min_nodes`
+        assert_eq!(
+            node_counts(&data(&topics), &settings),
+            vec![vec![0, 5, 50, 100]]
+        );
+    }
+
+    #[test]
+    fn all_zero_counts_give_no_nodes() {
+        let topics: [&[usize]; 2] = [&[0, 0, 0], &[0, 0, 0]];
+        for curve in [
+            Curve::Linear,
+            Curve::Log,
+            Curve::Sqrt,
+            Curve::CubicEase,
+            Curve::Quantile,
+        ]
+        .iter()
+        {
+            let settings = SimSettings {
+                count_curve: *curve,
+                min_nodes: 5,
+                ..SimSettings::default()
+            };
+            assert_eq!(
+                node_counts(&data(&topics), &settings),
+                vec![vec![0, 0, 0], vec![0, 0, 0]],
+                "{:?}",
+                curve
+            );
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// Picking which topics get a system of their own when there are more than
+// the map has room for.
+use crate::{Keyword, NodeGroupData, NodeGroupDataItem, SimSettings};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum TopicRank {
+    // By all their posts added up
+    Total,
+    // By their busiest bucket
+    Peak,
+}
+
+/// The topics to draw: pinned topics, then the biggest by `topic_rank`, up
+/// to `topic_limit` systems in all. The rest are added together into an
+/// `other_topic` system, if there is one, and otherwise left out. The kept
+/// topics are numbered again from 0 in their original order, with the other
+/// system last.
+pub fn select_topics(data: &NodeGroupData, settings: &SimSettings) -> NodeGroupData {
+    let limit = match settings.topic_limit {
+        Some(limit) if data.groups.len() > limit => limit,
+        _ => return renumber(data, data.groups.iter().collect(), None),
+    };
+    // Leave room for the other system
+    let limit = if settings.other_topic.is_some() {
+        limit.saturating_sub(1)
+    } else {
+        limit
+    };
+
+    let score = |item: &NodeGroupDataItem| match settings.topic_rank {
+        TopicRank::Total => item.counts.iter().sum::<usize>(),
+        TopicRank::Peak => item.counts.iter().cloned().max().unwrap_or(0),
+    };
+    let pinned = |item: &NodeGroupDataItem| settings.pinned_topics.contains(&item.key);
+
+    let mut ranked = (0..data.groups.len()).collect::<Vec<usize>>();
+    ranked.sort_by_key(|i| {
+        let item = &data.groups[*i];
+        (!pinned(item), std::cmp::Reverse(score(item)))
+    });
+    let pinned_count = data.groups.iter().filter(|item| pinned(item)).count();
+    let (kept, dropped) = ranked.split_at(limit.max(pinned_count).min(ranked.len()));
+
+    let mut kept = kept.to_vec();
+    kept.sort_unstable();
+    let kept = kept.iter().map(|i| &data.groups[*i]).collect();
+
+    let other = match &settings.other_topic {
+        Some(key) if !dropped.is_empty() => {
+            Some(combine(key, dropped.iter().map(|i| &data.groups[*i])))
+        }
+        _ => None,
+    };
+
+    renumber(data, kept, other)
+}
+
+fn renumber(
+    data: &NodeGroupData,
+    kept: Vec<&NodeGroupDataItem>,
+    other: Option<NodeGroupDataItem>,
+) -> NodeGroupData {
+    let groups = kept
+        .into_iter()
+        .cloned()
+        .chain(other)
+        .enumerate()
+        .map(|(index, item)| NodeGroupDataItem { index, ..item })
+        .collect();
+
+    NodeGroupData {
+        groups,
+        start_date: data.start_date,
+        end_date: data.end_date,
+        bucket: data.bucket,
+    }
+}
+
+// One topic holding the counts and keywords of all of `items`
+fn combine<'a>(key: &str, items: impl Iterator<Item = &'a NodeGroupDataItem>) -> NodeGroupDataItem {
+    let mut counts: Vec<usize> = Vec::new();
+    let mut keywords: Vec<Vec<Keyword>> = Vec::new();
+
+    for item in items {
+        if counts.len() < item.counts.len() {
+            counts.resize(item.counts.len(), 0);
+        }
+        for (total, count) in counts.iter_mut().zip(item.counts.iter()) {
+            *total += count;
+        }
+
+        if keywords.len() < item.keywords.len() {
+            keywords.resize(item.keywords.len(), Vec::new());
+        }
+        for (words, item_words) in keywords.iter_mut().zip(item.keywords.iter()) {
+            for keyword in item_words {
+                match words.iter_mut().find(|k| k.word == keyword.word) {
+                    Some(word) => word.weight += keyword.weight,
+                    None => words.push(keyword.clone()),
+                }
+            }
+        }
+    }
+
+    NodeGroupDataItem {
+        key: key.to_string(),
+        index: 0,
+        counts,
+        location: None,
+        keywords,
+        parent: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    // Topics a to d, d the biggest
+    fn data() -> NodeGroupData {
+        let topics: [(&str, &[usize]); 4] = [
+            ("a", &[1, 0]),
+            ("b", &[2, 5]),
+            ("c", &[10, 1]),
+            ("d", &[20, 3]),
+        ];
+        NodeGroupData {
+            groups: topics
+                .iter()
+                .enumerate()
+                .map(|(index, (key, counts))| NodeGroupDataItem {
+                    key: key.to_string(),
+                    index,
+                    counts: counts.to_vec(),
+                    location: None,
+                    keywords: Vec::new(),
+                    parent: None,
+                })
+                .collect(),
+            start_date: NaiveDate::from_ymd_opt(2021, 3, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2021, 3, 2).unwrap(),
+            bucket: Default::default(),
+        }
+    }
+
+    fn select(limit: Option<usize>, other: Option<&str>, pinned: &[&str]) -> NodeGroupData {
+        let settings = SimSettings {
+            topic_limit: limit,
+            other_topic: other.map(|o| o.to_string()),
+            pinned_topics: pinned.iter().map(|p| p.to_string()).collect(),
+            ..SimSettings::default()
+        };
+        select_topics(&data(), &settings)
+    }
+
+    fn keys(data: &NodeGroupData) -> Vec<&str> {
+        data.groups.iter().map(|g| g.key.as_str()).collect()
+    }
+
+    #[test]
+    fn pinned_topics_are_kept_beyond_the_limit() {
+        let selected = select(Some(2), None, &["a", "b", "c"]);
+        assert_eq!(keys(&selected), ["a", "b", "c"]);
+
+        let selected = select(Some(2), Some("other"), &["a", "b", "c"]);
+        assert_eq!(keys(&selected), ["a", "b", "c", "other"]);
+        assert_eq!(selected.groups[3].counts, [20, 3]);
+    }
+
+    #[test]
+    fn without_an_other_topic_the_rest_are_left_out() {
+        assert_eq!(keys(&select(Some(2), None, &[])), ["c", "d"]);
+        assert_eq!(keys(&select(None, None, &[])), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn tiny_limits() {
+        assert!(select(Some(0), None, &[]).groups.is_empty());
+        assert_eq!(keys(&select(Some(1), None, &[])), ["d"]);
+
+        // The other system takes the only place there is
+        for limit in [0, 1].iter() {
+            let selected = select(Some(*limit), Some("other"), &[]);
+            assert_eq!(keys(&selected), ["other"]);
+            assert_eq!(selected.groups[0].counts, [33, 9]);
+        }
+    }
+
+    #[test]
+    fn kept_topics_are_numbered_again_with_other_last() {
+        let selected = select(Some(3), Some("other"), &[]);
+        assert_eq!(keys(&selected), ["c", "d", "other"]);
+        let indices = selected
+            .groups
+            .iter()
+            .map(|g| g.index)
+            .collect::<Vec<usize>>();
+        assert_eq!(indices, [0, 1, 2]);
+        assert_eq!(selected.groups[2].counts, [3, 5]);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    format_date, midnight, Affinity, Attractor, Boundary, Bounds, Bucket, Curve, MapProjection,
+    NodeGroupData, Removal, Spawn, TopicRank, Topology,
+};
+use chrono::NaiveDate;
+use serde::{de::Error, Deserialize, Serialize};
+use serde_json::Result;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    // One GeoJSON FeatureCollection per captured frame
+    GeoJson,
+    // A single JSON file holding every captured frame
+    Timeline,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimSettings {
+    // Physics, stepped at a fixed rate whatever the frame rate. Velocities
+    // are in pixels per second, damping is the rate velocity decays per second
+    pub physics_rate: u32,
+    pub node_radius: f32,
+    pub node_max_velocity: f32,
+    pub node_attract_ramp: f32,
+    pub node_attract_strength: f32,
+    pub node_attract_strength_friendly: f32,
+    // How hard child topics are pulled towards the middle of their parent,
+    // negative pulls
+    pub parent_attract_strength: f32,
+    // Shifts the strength between groups by their affinity, scaled by
+    // `affinity_strength`, so related topics repel less or even attract
+    pub affinity: Option<Affinity>,
+    pub affinity_strength: f32,
+    pub node_damping: f32,
+    pub spring_damping: f32,
+    // What the groups are pulled towards
+    pub attractors: Vec<Attractor>,
+    // What keeps the nodes on screen
+    pub boundary: Boundary,
+    // Turns topic locations into window positions
+    pub map_projection: MapProjection,
+    // How post counts become node counts. The biggest count gets
+    // `count / count_divider` nodes, `max_nodes` of None means no limit
+    pub count_curve: Curve,
+    pub count_divider: f32,
+    pub min_nodes: usize,
+    pub max_nodes: Option<usize>,
+    // At most `topic_limit` systems, None for every topic. Pinned topics are
+    // always kept, then the biggest by `topic_rank`. The rest are added up
+    // into a system called `other_topic`, or left out if that's None
+    pub topic_limit: Option<usize>,
+    pub topic_rank: TopicRank,
+    pub other_topic: Option<String>,
+    pub pinned_topics: Vec<String>,
+    // How groups shrink and grow between buckets
+    pub node_removal: Removal,
+    pub node_spawn: Spawn,
+    // Springs, with a length and stiffness for each topology
+    pub spring_topology: Topology,
+    pub spring_rebuild_seconds: f32,
+    pub spring_length: f32,
+    pub spring_stiffness: f32,
+    pub ring_spring_length: f32,
+    pub ring_spring_stiffness: f32,
+    pub buddy_count: usize,
+    pub buddy_spring_length: f32,
+    pub buddy_spring_stiffness: f32,
+    pub nearest_count: usize,
+    pub nearest_spring_length: f32,
+    pub nearest_spring_stiffness: f32,
+    pub delaunay_spring_length: f32,
+    pub delaunay_spring_stiffness: f32,
+    // Hulls
+    pub hull_cluster_distance: f32,
+    pub hull_cluster_min_points: usize,
+    pub hull_concavity: f32,
+    pub hull_simplify: f32,
+    // Rendering
+    pub isobar_stroke_weight: f32,
+    pub label_font_size: u32,
+    // Keywords to set along each system's trough, 0 for no troughs
+    pub trough_keywords: usize,
+    pub trough_dash: f32,
+    // Draw the debug overlays into captured frames too
+    pub debug_in_capture: bool,
+    // Timing
+    pub frame_rate: u32,
+    // Seconds of video for each bucket of the data, an hour or a day or
+    // whatever the data was gathered in
+    pub bucket_seconds: u32,
+    // The longest to wait for the sim to settle before capturing. With
+    // `stabilize_auto` capture starts sooner, once the mean kinetic energy of
+    // the nodes and the change in hull area per frame have stayed under their
+    // thresholds for `stabilize_calm_time` seconds
+    pub stabilize_time: u32,
+    pub stabilize_auto: bool,
+    pub stabilize_energy: f32,
+    pub stabilize_hull_change: f32,
+    pub stabilize_calm_time: f32,
+    // Frame rate and speed up applied when the frames are turned into video
+    pub video_frame_rate: u32,
+    pub video_speed: f32,
+    pub export_format: Option<ExportFormat>,
+    // strftime style formats for the dates on screen and in the names of the
+    // output directories, e.g. "%d/%m/%Y". Day and month names are in English
+    pub date_format: String,
+    pub out_dir_format: String,
+    // Taken from the window and the data rather than the config file
+    #[serde(skip)]
+    pub repel_rect: Bounds,
+    #[serde(skip)]
+    pub start_date: NaiveDate,
+    #[serde(skip)]
+    pub end_date: NaiveDate,
+    #[serde(skip)]
+    pub bucket: Bucket,
+}
+
+impl Default for SimSettings {
+    fn default() -> Self {
+        SimSettings {
+            physics_rate: 60,
+            node_radius: 120.0,
+            node_attract_ramp: 1.4,
+            node_attract_strength: -1.5,
+            node_attract_strength_friendly: -1.0,
+            parent_attract_strength: -2.0,
+            affinity: None,
+            affinity_strength: 2.0,
+            // Keeps a tenth of the velocity every 1/20s, like the old
+            // per-frame damping of 0.9
+            node_damping: 46.0,
+            node_max_velocity: 40.0,
+            spring_damping: 0.9,
+            attractors: vec![Attractor::default()],
+            boundary: Boundary::SoftWall,
+            map_projection: MapProjection::default(),
+            count_curve: Curve::CubicEase,
+            count_divider: 50.0,
+            min_nodes: 0,
+            max_nodes: None,
+            topic_limit: None,
+            topic_rank: TopicRank::Total,
+            other_topic: Some("Other".to_string()),
+            pinned_topics: Vec::new(),
+            node_removal: Removal::Periphery,
+            node_spawn: Spawn::HullBoundary,
+            spring_topology: Topology::Star,
+            spring_rebuild_seconds: 1.0,
+            spring_length: 120.0 * 1.2,
+            spring_stiffness: 4.3,
+            ring_spring_length: 60.0,
+            ring_spring_stiffness: 2.0,
+            buddy_count: 3,
+            buddy_spring_length: 120.0,
+            buddy_spring_stiffness: 2.0,
+            nearest_count: 4,
+            nearest_spring_length: 40.0,
+            nearest_spring_stiffness: 2.0,
+            delaunay_spring_length: 40.0,
+            delaunay_spring_stiffness: 2.0,
+            hull_cluster_distance: 40.0,
+            hull_cluster_min_points: 20,
+            hull_concavity: 2.0,
+            hull_simplify: 1.0,
+            isobar_stroke_weight: 4.0,
+            label_font_size: 18,
+            trough_keywords: 3,
+            trough_dash: 8.0,
+            debug_in_capture: false,
+            frame_rate: 20,
+            bucket_seconds: 5,
+            stabilize_time: 10,
+            stabilize_auto: true,
+            stabilize_energy: 20.0,
+            stabilize_hull_change: 0.01,
+            stabilize_calm_time: 1.0,
+            video_frame_rate: 30,
+            video_speed: 2.0,
+            export_format: None,
+            date_format: "%a %-d %b".to_string(),
+            out_dir_format: "%Y-%m-%d".to_string(),
+            repel_rect: Bounds::default(),
+            start_date: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            bucket: Bucket::Day,
+        }
+    }
+}
+
+impl SimSettings {
+    pub fn new(repel_rect: Bounds, data: &NodeGroupData) -> Self {
+        SimSettings {
+            repel_rect,
+            start_date: data.start_date,
+            end_date: data.end_date,
+            bucket: data.bucket,
+            ..SimSettings::default()
+        }
+    }
+
+    /// Read settings from a config file, using the defaults if there isn't one.
+    pub fn from_file<P: AsRef<Path>>(
+        path: P,
+        repel_rect: Bounds,
+        data: &NodeGroupData,
+    ) -> Result<Self> {
+        let mut settings: SimSettings = match File::open(path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(_) => SimSettings::default(),
+        };
+        settings.check()?;
+        settings.repel_rect = repel_rect;
+        settings.set_data(data);
+        Ok(settings)
+    }
+
+    /// Read settings from the text of a config file, like `from_file`.
+    pub fn from_json(text: &str, repel_rect: Bounds, data: &NodeGroupData) -> Result<Self> {
+        let mut settings: SimSettings = serde_json::from_str(text)?;
+        settings.check()?;
+        settings.repel_rect = repel_rect;
+        settings.set_data(data);
+        Ok(settings)
+    }
+
+    /// Refuse the settings the frame counting would divide by zero on.
+    fn check(&self) -> Result<()> {
+        if self.frame_rate == 0 {
+            return Err(Error::custom("frame_rate has to be at least 1"));
+        }
+        if self.bucket_seconds == 0 {
+            return Err(Error::custom("bucket_seconds has to be at least 1"));
+        }
+        Ok(())
+    }
+
+    /// Take the dates and bucket size from newly loaded data.
+    pub fn set_data(&mut self, data: &NodeGroupData) {
+        self.start_date = data.start_date;
+        self.end_date = data.end_date;
+        self.bucket = data.bucket;
+    }
+
+    /// Where the frames and sidecar files for the data are written.
+    pub fn out_dir(&self) -> String {
+        format!(
+            "./out/{}",
+            format_date(midnight(self.start_date), &self.out_dir_format)
+        )
+    }
+
+    /// The spring length and stiffness for the current topology.
+    pub fn topology_spring(&self) -> (f32, f32) {
+        match self.spring_topology {
+            Topology::Star => (self.spring_length, self.spring_stiffness),
+            Topology::Ring => (self.ring_spring_length, self.ring_spring_stiffness),
+            Topology::Buddies => (self.buddy_spring_length, self.buddy_spring_stiffness),
+            Topology::Nearest => (self.nearest_spring_length, self.nearest_spring_stiffness),
+            Topology::Delaunay => (self.delaunay_spring_length, self.delaunay_spring_stiffness),
+        }
+    }
+
+    pub fn topology_spring_mut(&mut self) -> (&mut f32, &mut f32) {
+        match self.spring_topology {
+            Topology::Star => (&mut self.spring_length, &mut self.spring_stiffness),
+            Topology::Ring => (
+                &mut self.ring_spring_length,
+                &mut self.ring_spring_stiffness,
+            ),
+            Topology::Buddies => (
+                &mut self.buddy_spring_length,
+                &mut self.buddy_spring_stiffness,
+            ),
+            Topology::Nearest => (
+                &mut self.nearest_spring_length,
+                &mut self.nearest_spring_stiffness,
+            ),
+            Topology::Delaunay => (
+                &mut self.delaunay_spring_length,
+                &mut self.delaunay_spring_stiffness,
+            ),
+        }
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path).map_err(serde_json::Error::io)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)
+    }
+}
@@ -0,0 +1,68 @@
+// Watching how much the simulation is still moving, to tell when it has settled.
+use crate::{hull_area, NodeGroup, SimSettings};
+
+pub struct Stability {
+    // Hull area of each group last frame
+    areas: Vec<f32>,
+    calm_seconds: f32,
+    // Mean kinetic energy of a node, in pixels squared per second squared
+    pub kinetic_energy: f32,
+    // The biggest change in a group's hull area since last frame, as a fraction
+    pub hull_change: f32,
+}
+
+impl Default for Stability {
+    fn default() -> Self {
+        Stability {
+            areas: Vec::new(),
+            calm_seconds: 0.0,
+            kinetic_energy: 0.0,
+            hull_change: 1.0,
+        }
+    }
+}
+
+impl Stability {
+    pub fn new() -> Self {
+        Stability::default()
+    }
+
+    /// Measure the frame that was just stepped. Returns true once both
+    /// measures have stayed under their thresholds for long enough.
+    pub fn update(&mut self, groups: &[NodeGroup], settings: &SimSettings) -> bool {
+        let mut energy = 0.0;
+        let mut node_count = 0;
+        for group in groups.iter() {
+            for node in group.nodes() {
+                energy += 0.5 * node.velocity.length_squared();
+                node_count += 1;
+            }
+        }
+        self.kinetic_energy = energy / node_count.max(1) as f32;
+
+        let areas = groups.iter().map(total_hull_area).collect::<Vec<f32>>();
+        self.hull_change = if areas.len() == self.areas.len() {
+            areas
+                .iter()
+                .zip(self.areas.iter())
+                .map(|(area, last)| (area - last).abs() / last.max(1.0))
+                .fold(0.0, f32::max)
+        } else {
+            1.0
+        };
+        self.areas = areas;
+
+        if self.kinetic_energy < settings.stabilize_energy
+            && self.hull_change < settings.stabilize_hull_change
+        {
+            self.calm_seconds += 1.0 / settings.frame_rate.max(1) as f32;
+        } else {
+            self.calm_seconds = 0.0;
+        }
+        self.calm_seconds >= settings.stabilize_calm_time
+    }
+}
+
+fn total_hull_area(group: &NodeGroup) -> f32 {
+    group.convex_hulls.iter().map(hull_area).sum()
+}
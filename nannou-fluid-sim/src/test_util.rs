@@ -0,0 +1,12 @@
+// Helpers shared by the tests of the data subcommands.
+use chrono::NaiveDate;
+use nannou_fluid_sim::NodeGroupData;
+
+pub fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+// The counts of the topic with `key`, which has to be in the data
+pub fn counts<'a>(data: &'a NodeGroupData, key: &str) -> &'a [usize] {
+    &data.groups.iter().find(|g| g.key == key).unwrap().counts
+}
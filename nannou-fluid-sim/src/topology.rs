@@ -0,0 +1,307 @@
+// The different ways the nodes of a group can be tied together with springs.
+use crate::{random_range, Node, SimSettings, Spring};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Topology {
+    // Every node tied to the first node
+    Star,
+    // Nodes tied to their neighbours, in order around the centre of the group
+    Ring,
+    // Every node tied to a few random others
+    Buddies,
+    // Every node tied to its nearest neighbours, rebuilt as the group moves
+    Nearest,
+    // The edges of a Delaunay triangulation, rebuilt as the group moves
+    Delaunay,
+}
+
+impl Topology {
+    pub fn next(self) -> Self {
+        match self {
+            Topology::Star => Topology::Ring,
+            Topology::Ring => Topology::Buddies,
+            Topology::Buddies => Topology::Nearest,
+            Topology::Nearest => Topology::Delaunay,
+            Topology::Delaunay => Topology::Star,
+        }
+    }
+
+    /// Whether the springs depend on where the nodes are, so need rebuilding.
+    pub fn follows_positions(self) -> bool {
+        matches!(self, Topology::Nearest | Topology::Delaunay)
+    }
+}
+
+/// Add springs for the nodes from `first_new` onwards, keeping the existing
+/// ones. Returns false if the topology has to be rebuilt from scratch instead.
+pub fn extend_connections(
+    springs: &mut Vec<Spring>,
+    nodes: &[Node],
+    first_new: usize,
+    settings: &SimSettings,
+) -> bool {
+    let (length, stiffness) = settings.topology_spring();
+    let new_spring = |from, to| Spring {
+        from,
+        to,
+        length,
+        stiffness,
+        damping: settings.spring_damping,
+    };
+
+    match settings.spring_topology {
+        Topology::Star if first_new > 0 => {
+            springs.extend((first_new..nodes.len()).map(|j| new_spring(0, j)));
+            true
+        }
+        Topology::Buddies => {
+            for i in first_new..nodes.len() {
+                for _ in 0..settings.buddy_count {
+                    let buddy = random_range(0, nodes.len());
+                    if buddy != i {
+                        springs.push(new_spring(i, buddy));
+                    }
+                }
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Whether the springs left after removing nodes still make sense, rather
+/// than needing rebuilding.
+pub fn survives_removal(topology: Topology) -> bool {
+    matches!(topology, Topology::Star | Topology::Buddies)
+}
+
+pub fn create_connections(nodes: &[Node], settings: &SimSettings) -> Vec<Spring> {
+    let edges = match settings.spring_topology {
+        Topology::Star => star_edges(nodes.len()),
+        Topology::Ring => ring_edges(nodes),
+        Topology::Buddies => buddy_edges(nodes.len(), settings.buddy_count),
+        Topology::Nearest => nearest_edges(nodes, settings.nearest_count),
+        Topology::Delaunay => delaunay_edges(nodes),
+    };
+    let (length, stiffness) = settings.topology_spring();
+
+    edges
+        .into_iter()
+        .map(|(from, to)| Spring {
+            from,
+            to,
+            length,
+            stiffness,
+            damping: settings.spring_damping,
+        })
+        .collect::<Vec<Spring>>()
+}
+
+fn star_edges(group_size: usize) -> Vec<(usize, usize)> {
+    (1..group_size).map(|j| (0, j)).collect()
+}
+
+fn ring_edges(nodes: &[Node]) -> Vec<(usize, usize)> {
+    if nodes.len() < 2 {
+        return Vec::new();
+    }
+
+    let count = nodes.len() as f32;
+    let cx = nodes.iter().map(|n| n.x).sum::<f32>() / count;
+    let cy = nodes.iter().map(|n| n.y).sum::<f32>() / count;
+
+    let mut order = (0..nodes.len()).collect::<Vec<usize>>();
+    let angle = |i: &usize| (nodes[*i].y - cy).atan2(nodes[*i].x - cx);
+    order.sort_by(|a, b| {
+        angle(a)
+            .partial_cmp(&angle(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    (0..order.len())
+        .map(|i| (order[i], order[(i + 1) % order.len()]))
+        .collect()
+}
+
+fn buddy_edges(group_size: usize, buddy_count: usize) -> Vec<(usize, usize)> {
+    if group_size < 2 {
+        return Vec::new();
+    }
+
+    let mut edges = HashSet::new();
+    for i in 0..group_size {
+        for _ in 0..buddy_count {
+            let buddy = random_range(0, group_size);
+            if buddy != i {
+                edges.insert(ordered(i, buddy));
+            }
+        }
+    }
+    edges.into_iter().collect()
+}
+
+fn nearest_edges(nodes: &[Node], neighbour_count: usize) -> Vec<(usize, usize)> {
+    let mut edges = HashSet::new();
+    for i in 0..nodes.len() {
+        let mut others = (0..nodes.len())
+            .filter(|j| *j != i)
+            .map(|j| (j, distance_squared(&nodes[i], &nodes[j])))
+            .collect::<Vec<(usize, f32)>>();
+        others.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        for (j, _) in others.into_iter().take(neighbour_count) {
+            edges.insert(ordered(i, j));
+        }
+    }
+    edges.into_iter().collect()
+}
+
+// Bowyer-Watson triangulation. Nodes sitting exactly on top of another are
+// left out until they have drifted apart.
+fn delaunay_edges(nodes: &[Node]) -> Vec<(usize, usize)> {
+    let n = nodes.len();
+    if n < 3 {
+        return star_edges(n);
+    }
+
+    let mut points = nodes
+        .iter()
+        .map(|node| (node.x as f64, node.y as f64))
+        .collect::<Vec<(f64, f64)>>();
+
+    // A triangle big enough to hold every point
+    let (mut min_x, mut min_y, mut max_x, mut max_y) =
+        (points[0].0, points[0].1, points[0].0, points[0].1);
+    for &(x, y) in points.iter() {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    let size = (max_x - min_x).max(max_y - min_y).max(1.0);
+    let (mid_x, mid_y) = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+    points.push((mid_x - 20.0 * size, mid_y - size));
+    points.push((mid_x + 20.0 * size, mid_y - size));
+    points.push((mid_x, mid_y + 20.0 * size));
+
+    let mut triangles = vec![[n, n + 1, n + 2]];
+
+    for i in 0..n {
+        let p = points[i];
+        let (bad, good): (Vec<[usize; 3]>, Vec<[usize; 3]>) = triangles
+            .into_iter()
+            .partition(|t| in_circumcircle(&points, t, p));
+
+        // The edges of the hole left by the bad triangles appear only once
+        let mut hole: Vec<(usize, usize)> = Vec::new();
+        for t in bad.iter() {
+            for &(a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])].iter() {
+                match hole.iter().position(|&e| e == (b, a) || e == (a, b)) {
+                    Some(index) => {
+                        hole.swap_remove(index);
+                    }
+                    None => hole.push((a, b)),
+                }
+            }
+        }
+
+        triangles = good;
+        for (a, b) in hole {
+            triangles.push(counter_clockwise(&points, [a, b, i]));
+        }
+    }
+
+    let mut edges = HashSet::new();
+    for t in triangles.iter().filter(|t| t.iter().all(|v| *v < n)) {
+        edges.insert(ordered(t[0], t[1]));
+        edges.insert(ordered(t[1], t[2]));
+        edges.insert(ordered(t[2], t[0]));
+    }
+    if edges.is_empty() {
+        // Points in a line only make triangles with the outer corners, so tie
+        // each to the next along the line instead
+        return line_edges(&points[..n]);
+    }
+    edges.into_iter().collect()
+}
+
+fn line_edges(points: &[(f64, f64)]) -> Vec<(usize, usize)> {
+    let mut order = (0..points.len()).collect::<Vec<usize>>();
+    order.sort_by(|a, b| {
+        points[*a]
+            .partial_cmp(&points[*b])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    order.windows(2).map(|w| ordered(w[0], w[1])).collect()
+}
+
+fn counter_clockwise(points: &[(f64, f64)], t: [usize; 3]) -> [usize; 3] {
+    let (a, b, c) = (points[t[0]], points[t[1]], points[t[2]]);
+    let cross = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+    if cross < 0.0 {
+        [t[0], t[2], t[1]]
+    } else {
+        t
+    }
+}
+
+fn in_circumcircle(points: &[(f64, f64)], t: &[usize; 3], p: (f64, f64)) -> bool {
+    let (a, b, c) = (points[t[0]], points[t[1]], points[t[2]]);
+    let (ax, ay) = (a.0 - p.0, a.1 - p.1);
+    let (bx, by) = (b.0 - p.0, b.1 - p.1);
+    let (cx, cy) = (c.0 - p.0, c.1 - p.1);
+    let det = (ax * ax + ay * ay) * (bx * cy - cx * by) - (bx * bx + by * by) * (ax * cy - cx * ay)
+        + (cx * cx + cy * cy) * (ax * by - bx * ay);
+    det > 0.0
+}
+
+fn distance_squared(a: &Node, b: &Node) -> f32 {
+    (a.x - b.x).powi(2) + (a.y - b.y).powi(2)
+}
+
+fn ordered(a: usize, b: usize) -> (usize, usize) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes(points: &[(f32, f32)]) -> Vec<Node> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(id, &(x, y))| Node::new(x, y, 1.0, 1.0, 1.0, 1.0, id))
+            .collect()
+    }
+
+    fn sorted(mut edges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        edges.sort();
+        edges
+    }
+
+    #[test]
+    fn a_square_gets_its_sides_and_one_diagonal() {
+        let square = nodes(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        let edges = sorted(delaunay_edges(&square));
+
+        assert_eq!(edges.len(), 5);
+        for side in [(0, 1), (1, 2), (2, 3), (0, 3)].iter() {
+            assert!(edges.contains(side), "{:?} missing from {:?}", side, edges);
+        }
+        assert!(edges.contains(&(0, 2)) != edges.contains(&(1, 3)));
+    }
+
+    #[test]
+    fn points_in_a_line_are_tied_to_their_neighbours() {
+        let line = nodes(&[(0.0, 0.0), (20.0, 0.0), (10.0, 0.0), (30.0, 0.0)]);
+        let edges = sorted(delaunay_edges(&line));
+        assert_eq!(edges, vec![(0, 2), (1, 2), (1, 3)]);
+    }
+}
@@ -0,0 +1,110 @@
+// Troughs: the dashed lines running out from the middle of a system on a
+// weather map, here carrying the topic's most notable keywords.
+use crate::{hull_area, NodeGroup, SimSettings};
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Keyword {
+    pub word: String,
+    // How notable the word is, only compared against the topic's other words
+    pub weight: f32,
+}
+
+pub struct Trough {
+    pub start: Vec2,
+    pub end: Vec2,
+    // Heaviest first
+    pub keywords: Vec<String>,
+}
+
+// Shorter than this and the keywords won't fit along it
+const MIN_LENGTH: f32 = 60.0;
+
+/// A trough along the long axis of the group's biggest hull, from near its
+/// centre out towards its edge, carrying the `trough_keywords` heaviest
+/// keywords for `bucket`.
+pub fn group_trough(group: &NodeGroup, bucket: usize, settings: &SimSettings) -> Option<Trough> {
+    let mut keywords = group.keywords.get(bucket)?.clone();
+    keywords.sort_by(|a, b| {
+        b.weight
+            .partial_cmp(&a.weight)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    keywords.truncate(settings.trough_keywords);
+    if keywords.is_empty() {
+        return None;
+    }
+
+    let hull = group.convex_hulls.iter().max_by(|a, b| {
+        hull_area(a)
+            .partial_cmp(&hull_area(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })?;
+    let points = hull.0.iter().map(|c| vec2(c.x, c.y)).collect::<Vec<Vec2>>();
+    if points.len() < 3 {
+        return None;
+    }
+
+    // The long axis is the direction the points spread furthest in
+    let centre = points.iter().fold(vec2(0.0, 0.0), |sum, p| sum + *p) / points.len() as f32;
+    let (mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0);
+    for p in points.iter() {
+        let d = *p - centre;
+        sxx += d.x * d.x;
+        syy += d.y * d.y;
+        sxy += d.x * d.y;
+    }
+    let angle = 0.5 * (2.0 * sxy).atan2(sxx - syy);
+    let mut axis = vec2(angle.cos(), angle.sin());
+
+    // Run out along whichever half of the axis is longer
+    let along = points.iter().map(|p| (*p - centre).dot(axis));
+    let (min, max) = along.fold((0.0f32, 0.0f32), |(min, max), a| (min.min(a), max.max(a)));
+    let reach = if -min > max {
+        axis = -axis;
+        -min
+    } else {
+        max
+    };
+
+    // Leave the middle clear for the label
+    let start = centre + axis * reach * 0.3;
+    let end = centre + axis * reach * 0.95;
+    if (end - start).length() < MIN_LENGTH {
+        return None;
+    }
+
+    Some(Trough {
+        start,
+        end,
+        keywords: keywords.into_iter().map(|k| k.word).collect(),
+    })
+}
+
+impl Trough {
+    /// The dashes making up the line, each `dash` long with a gap as long
+    /// between them.
+    pub fn dashes(&self, dash: f32) -> Vec<(Vec2, Vec2)> {
+        let length = (self.end - self.start).length();
+        let direction = (self.end - self.start) / length;
+        let dash = dash.max(1.0);
+
+        let mut dashes = Vec::new();
+        let mut d = 0.0;
+        while d < length {
+            let to = (d + dash).min(length);
+            dashes.push((self.start + direction * d, self.start + direction * to));
+            d += dash * 2.0;
+        }
+        dashes
+    }
+
+    /// Where to put each keyword, spaced evenly along the line.
+    pub fn keyword_positions(&self) -> Vec<Vec2> {
+        let n = self.keywords.len() as f32;
+        (0..self.keywords.len())
+            .map(|i| self.start.lerp(self.end, (i as f32 + 0.5) / n))
+            .collect()
+    }
+}
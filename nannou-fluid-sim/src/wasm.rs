@@ -0,0 +1,155 @@
+// JS-facing API for running the simulation live in the browser.
+//
+//     const sim = new Simulation(dataJson, 800, 800, configJson);
+//     sim.set_bucket(0);
+//     sim.step(elapsedSeconds);
+//     const groups = JSON.parse(sim.hulls());
+use crate::{
+    build_groups, group_geometry, group_trough, set_bucket, step_seconds, Bounds, Clock, NodeGroup,
+    NodeGroupData, SimSettings,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+// A tab left in the background comes back with a long gap since the last
+// frame, which isn't worth catching up on
+const MAX_STEP_SECONDS: f64 = 0.25;
+
+#[derive(Serialize)]
+struct GroupHulls {
+    id: usize,
+    key: String,
+    parent: Option<String>,
+    value: usize,
+    label: Option<[f32; 2]>,
+    hulls: Vec<Vec<[f32; 2]>>,
+    rings: Vec<Vec<[f32; 2]>>,
+    trough: Option<[[f32; 2]; 2]>,
+    keywords: Vec<String>,
+}
+
+#[wasm_bindgen]
+pub struct Simulation {
+    node_groups: Vec<NodeGroup>,
+    clock: Clock,
+    settings: SimSettings,
+    bucket: usize,
+    label_bounds: Bounds,
+}
+
+#[wasm_bindgen]
+impl Simulation {
+    /// Load a `data.json` produced by data-gen, for a canvas of the given size.
+    /// `settings_json` is a `config.json` to match a render with, otherwise the
+    /// defaults are used.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        data_json: &str,
+        width: f32,
+        height: f32,
+        settings_json: Option<String>,
+    ) -> Result<Simulation, JsValue> {
+        let data: NodeGroupData =
+            serde_json::from_str(data_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        // Every group starts on its first bucket
+        if let Some(item) = data.groups.iter().find(|g| g.counts.is_empty()) {
+            return Err(JsValue::from_str(&format!("{} has no counts", item.key)));
+        }
+
+        let window_rect = Bounds::from_w_h(width, height);
+        let repel_rect = window_rect.pad(32.0);
+        let settings = match settings_json {
+            Some(json) => SimSettings::from_json(&json, repel_rect, &data)
+                .map_err(|e| JsValue::from_str(&format!("settings: {}", e)))?,
+            None => SimSettings::new(repel_rect, &data),
+        };
+
+        let node_groups = build_groups(&data, &settings);
+
+        Ok(Simulation {
+            node_groups,
+            clock: Clock::new(),
+            settings,
+            bucket: 0,
+            label_bounds: window_rect.pad(96.0),
+        })
+    }
+
+    pub fn bucket_count(&self) -> usize {
+        self.node_groups
+            .iter()
+            .map(|g| g.bucket_values.len())
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn bucket(&self) -> usize {
+        self.bucket
+    }
+
+    /// How many seconds of real time each bucket covers, 3600 for hourly data.
+    /// Not the `bucket_seconds` setting, which is seconds of video.
+    pub fn bucket_duration_seconds(&self) -> f64 {
+        self.settings.bucket.seconds() as f64
+    }
+
+    /// Move on to `bucket`, which has to be below `bucket_count()`.
+    pub fn set_bucket(&mut self, bucket: usize) -> Result<(), JsValue> {
+        if bucket >= self.bucket_count() {
+            return Err(JsValue::from_str(&format!(
+                "bucket {} is out of range, there are {}",
+                bucket,
+                self.bucket_count()
+            )));
+        }
+        self.bucket = bucket;
+        set_bucket(&mut self.node_groups, bucket, &self.settings);
+        Ok(())
+    }
+
+    /// Run the simulation on by `seconds`, the time since the last frame.
+    pub fn step(&mut self, seconds: f64) {
+        let seconds = seconds.clamp(0.0, MAX_STEP_SECONDS);
+        step_seconds(
+            &mut self.node_groups,
+            &self.settings,
+            &mut self.clock,
+            seconds,
+        );
+    }
+
+    /// The current hulls and isobar rings of every group, as JSON.
+    pub fn hulls(&self) -> String {
+        let groups = self
+            .node_groups
+            .iter()
+            .map(|group| {
+                let geometry = group_geometry(group, self.label_bounds);
+                let trough = group_trough(group, self.bucket, &self.settings);
+                GroupHulls {
+                    id: group.id,
+                    key: group.label.clone(),
+                    parent: group.parent.clone(),
+                    value: group.display_values.get(self.bucket).cloned().unwrap_or(0),
+                    label: geometry.label_pos.map(|p| [p.x, p.y]),
+                    hulls: group
+                        .convex_hulls
+                        .iter()
+                        .map(|hull| hull.0.iter().map(|c| [c.x, c.y]).collect())
+                        .collect(),
+                    rings: geometry
+                        .rings
+                        .iter()
+                        .map(|ring| ring.iter().map(|p| [p.x, p.y]).collect())
+                        .collect(),
+                    trough: trough
+                        .as_ref()
+                        .map(|t| [[t.start.x, t.start.y], [t.end.x, t.end.y]]),
+                    keywords: trough.map(|t| t.keywords).unwrap_or_default(),
+                }
+            })
+            .collect::<Vec<GroupHulls>>();
+
+        serde_json::to_string(&groups).unwrap()
+    }
+}